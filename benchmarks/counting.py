@@ -0,0 +1,85 @@
+import random
+from apriori import generate_frequent_itemsets
+
+
+def get_data(num_transactions=20000, num_items=500, num_patterns=50, seed=0):
+    """Synthetic transactions: random items plus a few frequent patterns each."""
+    rng = random.Random(seed)
+    items = [f"item{i}" for i in range(num_items)]
+    patterns = [rng.sample(items, rng.randint(3, 6)) for _ in range(num_patterns)]
+
+    transactions = []
+    for _ in range(num_transactions):
+        transaction = set(rng.sample(items, rng.randint(2, 10)))
+        for pattern in rng.sample(patterns, rng.randint(1, 3)):
+            transaction.update(pattern)
+        transactions.append(transaction)
+    return transactions
+
+
+def get_dense_data(num_transactions=20000, num_items=60, seed=1):
+    """Synthetic transactions holding about 40% of all items each."""
+    rng = random.Random(seed)
+    return [
+        set(f"item{i}" for i in range(num_items)
+            if rng.random() < (0.7 if i % 3 == 0 else 0.3))
+        for _ in range(num_transactions)
+    ]
+
+
+def get_params():
+    return [0.02, 0.01, 0.005], 5
+
+
+def get_dense_params():
+    return [0.2, 0.1], 4
+
+
+def elapsed_from_size_2(transactions, min_support, length, **kwargs):
+    """Seconds taken by itemset sizes 2 to `length`, i.e. after the first pass"""
+    elapsed = {}
+    generate_frequent_itemsets(
+        transactions, min_support, length,
+        progress=lambda size, _, __, seconds: elapsed.update({size: seconds}),
+        **kwargs)
+    return elapsed[length] - elapsed[1]
+
+
+def format_seconds(seconds):
+    return f"{seconds:.2f}s" if seconds < 10 else f"{seconds:.0f}s"
+
+
+def run(transactions, params, countings):
+    """Print a table row per minimum support, as in the README"""
+    min_supports, length = params
+    print("| Min support | " + " | ".join(countings) + " |")
+    print("|:-----------:|" + "|".join("-" * (len(c) + 1) + ":" for c in countings) + "|")
+    for min_support in min_supports:
+        times = [
+            elapsed_from_size_2(transactions, min_support, length, counting=counting)
+            for counting in countings
+        ]
+        print(f"| {min_support:>11} | " + " | ".join(map(format_seconds, times)) + " |")
+    print()
+
+
+def run_relabel(transactions, min_support, length):
+    """Print the time taken by sizes 2 and up without and with relabeling"""
+    times = [
+        elapsed_from_size_2(transactions, min_support, length, relabel=relabel)
+        for relabel in [None, "ascending", "descending"]
+    ]
+    print(f"support {min_support}: none {format_seconds(times[0])}, "
+          f"ascending {format_seconds(times[1])}, descending {format_seconds(times[2])}")
+
+
+def run_benchmark():
+    sparse, dense = get_data(), get_dense_data()
+    run(sparse, get_params(), ["scan", "trie", "bitset"])
+    run(dense, get_dense_params(), ["trie", "bitset"])
+    run_relabel(sparse, 0.005, get_params()[1])
+    run_relabel(dense, 0.1, get_dense_params()[1])
+
+
+if __name__ == "__main__":
+    run_benchmark()
@@ -0,0 +1,700 @@
+//! Command-line interface for batch mining of transaction files.
+#![allow(non_snake_case)]
+
+use std::{
+    env, fmt, fs,
+    io::{self, BufReader, BufWriter, Write},
+    process,
+};
+
+use apriori::{
+    error::Error,
+    interner::Interner,
+    io::{
+        read::{read_transactions, read_transactions_from, Format},
+        write::{write_itemsets, write_rules, OutputFormat},
+    },
+    itemsets::{
+        count::{
+            generate_frequent_and_infrequent_itemsets_from_transactions_with_options,
+            generate_frequent_itemsets_from_transactions_with_options, Counting, SupportOrder,
+        },
+        partition::generate_frequent_itemsets_from_file,
+        sample::{generate_frequent_itemsets_sampled, Sampling},
+        stream::LossyCounter,
+    },
+    progress::{Budget, BudgetExceeded, MiningOptions},
+    recommend::{Recommender, Scoring},
+    rules::{
+        rule::sort_rules,
+        search::{generate_negative_rules, generate_rules},
+    },
+    types::{FrequentItemsets, Transaction},
+};
+
+const USAGE: &str = "\
+Usage: apriori <COMMAND> [OPTIONS] <INPUT>
+
+Mine frequent itemsets and association rules from a transaction file. INPUT
+is a path, or - for standard input.
+
+Commands:
+  itemsets    Write frequent itemsets
+  rules       Write association rules
+  stats       Write statistics of the transactions
+  recommend   Write recommended items for a basket
+
+Options:
+  -s, --min-support <FLOAT>     Minimum support [default: 0.1]
+  -c, --min-confidence <FLOAT>  Minimum confidence [default: 0.5]
+  -k, --max-length <INT>        Maximum no. of items in an itemset [default: 3]
+  -a, --algorithm <NAME>        apriori, lossy or sample [default: apriori]
+  -e, --epsilon <FLOAT>         Maximum support error of lossy [default: min support / 10]
+      --sample-size <INT>       Transactions in the sample of sample [default: 10%]
+      --seed <INT>              Seed of the sample of sample [default: random]
+      --max-candidates <INT>    Stop before an itemset size with more candidates (not lossy)
+      --max-memory <BYTES>      Stop before an itemset size whose candidates take more
+                                memory (not lossy)
+      --chunk-size <INT>        Read INPUT twice in chunks of this many transactions, to
+                                mine files larger than memory (apriori, not long)
+      --counting <NAME>         Count candidates by auto, scan, trie or bitset
+                                [default: auto] (not lossy)
+      --relabel <ORDER>         Relabel frequent items by descending or ascending support
+                                before counting pairs (not lossy)
+  -f, --format <NAME>           Input format: basket, spmf or long [default: basket]
+  -d, --delimiter <CHAR>        Delimiter of the input [default: ,]
+      --header                  The first line of a long input is a header
+  -o, --output <PATH>           Output path [default: standard output]
+  -t, --output-format <NAME>    spmf, csv or jsonl [default: spmf]
+      --negative                Write negative rules (rules, apriori without --chunk-size)
+  -b, --basket <ITEMS>          Items of the basket, separated by the delimiter (recommend)
+  -n, --top <INT>               No. of recommended items [default: 10] (recommend)
+  -m, --method <NAME>           confidence, lift or vote [default: confidence] (recommend)
+  -h, --help                    Print this help
+";
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(io::Error),
+    Mining(Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(e) => write!(f, "{}\n\n{}", e, USAGE),
+            CliError::Io(e) => write!(f, "{}", e),
+            CliError::Mining(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
+
+impl From<Error> for CliError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::InvalidParameter(e) => CliError::Usage(e),
+            Error::Io(e) => CliError::Io(e),
+            e => CliError::Mining(e),
+        }
+    }
+}
+
+fn usage<T>(message: String) -> Result<T, CliError> {
+    Err(CliError::Usage(message))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    Itemsets,
+    Rules,
+    Stats,
+    Recommend,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    Apriori,
+    Lossy,
+    Sample,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    command: Command,
+    input: String,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    algorithm: Algorithm,
+    epsilon: Option<f32>,
+    sample_size: Option<usize>,
+    seed: Option<u64>,
+    budget: Budget,
+    chunk_size: Option<usize>,
+    counting: Counting,
+    relabel: Option<SupportOrder>,
+    format: Format,
+    output: Option<String>,
+    output_format: OutputFormat,
+    negative: bool,
+    basket: Vec<String>,
+    top: usize,
+    scoring: Scoring,
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Args>, CliError> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        Some("itemsets") => Command::Itemsets,
+        Some("rules") => Command::Rules,
+        Some("stats") => Command::Stats,
+        Some("recommend") => Command::Recommend,
+        Some("-h") | Some("--help") | None => return Ok(None),
+        Some(command) => return usage(format!("unknown command {:?}", command)),
+    };
+
+    let mut input = None;
+    let mut min_support = 0.1;
+    let mut min_confidence = 0.5;
+    let mut max_length = 3;
+    let mut algorithm = Algorithm::Apriori;
+    let mut epsilon = None;
+    let mut sample_size = None;
+    let mut seed = None;
+    let mut budget = Budget::default();
+    let mut chunk_size = None;
+    let mut counting = None;
+    let mut relabel = None;
+    let mut format = String::from("basket");
+    let mut delimiter = ',';
+    let mut header = false;
+    let mut output = None;
+    let mut output_format = OutputFormat::Spmf;
+    let mut negative = false;
+    let mut basket = None;
+    let mut top = 10;
+    let mut scoring = Scoring::MaxConfidence;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| CliError::Usage(format!("missing value of {}", name)))
+        };
+        match arg.as_str() {
+            "-s" | "--min-support" => min_support = parse(&arg, &value(&arg)?)?,
+            "-c" | "--min-confidence" => min_confidence = parse(&arg, &value(&arg)?)?,
+            "-k" | "--max-length" => max_length = parse(&arg, &value(&arg)?)?,
+            "-a" | "--algorithm" => {
+                algorithm = match value(&arg)?.as_str() {
+                    "apriori" => Algorithm::Apriori,
+                    "lossy" => Algorithm::Lossy,
+                    "sample" => Algorithm::Sample,
+                    name => return usage(format!("unknown algorithm {:?}", name)),
+                }
+            }
+            "-e" | "--epsilon" => epsilon = Some(parse(&arg, &value(&arg)?)?),
+            "--sample-size" => sample_size = Some(parse(&arg, &value(&arg)?)?),
+            "--seed" => seed = Some(parse(&arg, &value(&arg)?)?),
+            "--max-candidates" => budget.max_candidates = Some(parse(&arg, &value(&arg)?)?),
+            "--max-memory" => budget.max_memory = Some(parse(&arg, &value(&arg)?)?),
+            "--chunk-size" => chunk_size = Some(parse(&arg, &value(&arg)?)?),
+            "--counting" => {
+                counting = Some(match value(&arg)?.as_str() {
+                    "auto" => Counting::Auto,
+                    "scan" => Counting::Scan,
+                    "trie" => Counting::Trie,
+                    "bitset" => Counting::Bitset,
+                    name => return usage(format!("unknown counting {:?}", name)),
+                })
+            }
+            "--relabel" => {
+                relabel = match value(&arg)?.as_str() {
+                    "descending" => Some(SupportOrder::Descending),
+                    "ascending" => Some(SupportOrder::Ascending),
+                    name => return usage(format!("unknown relabel order {:?}", name)),
+                }
+            }
+            "-f" | "--format" => format = value(&arg)?,
+            "-d" | "--delimiter" => delimiter = parse(&arg, &value(&arg)?)?,
+            "--header" => header = true,
+            "-o" | "--output" => output = Some(value(&arg)?),
+            "-t" | "--output-format" => {
+                output_format = match value(&arg)?.as_str() {
+                    "spmf" => OutputFormat::Spmf,
+                    "csv" => OutputFormat::Csv,
+                    "jsonl" => OutputFormat::JsonLines,
+                    name => return usage(format!("unknown output format {:?}", name)),
+                }
+            }
+            "--negative" => negative = true,
+            "-b" | "--basket" => basket = Some(value(&arg)?),
+            "-n" | "--top" => top = parse(&arg, &value(&arg)?)?,
+            "-m" | "--method" => {
+                scoring = match value(&arg)?.as_str() {
+                    "confidence" => Scoring::MaxConfidence,
+                    "lift" => Scoring::SumLift,
+                    "vote" => Scoring::WeightedVote,
+                    name => return usage(format!("unknown method {:?}", name)),
+                }
+            }
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return usage(format!("unknown option {:?}", arg))
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => return usage(format!("unexpected argument {:?}", arg)),
+        }
+    }
+    let format = match format.as_str() {
+        "basket" => Format::Basket { delimiter },
+        "spmf" => Format::Spmf,
+        "long" => Format::Long {
+            delimiter,
+            has_header: header,
+        },
+        name => return usage(format!("unknown format {:?}", name)),
+    };
+    if !(0.0..=1.0).contains(&min_support) || !(0.0..=1.0).contains(&min_confidence) {
+        return usage(String::from(
+            "min support and min confidence must be between 0 and 1",
+        ));
+    }
+    if max_length == 0 {
+        return usage(String::from("max length must be at least 1"));
+    }
+    if let Some(chunk_size) = chunk_size {
+        if chunk_size == 0 {
+            return usage(String::from("chunk size must be at least 1"));
+        }
+        if algorithm != Algorithm::Apriori || matches!(format, Format::Long { .. }) {
+            return usage(String::from(
+                "--chunk-size requires the apriori algorithm and a basket or spmf INPUT",
+            ));
+        }
+        if input.as_deref() == Some("-") {
+            return usage(String::from("--chunk-size cannot read standard input"));
+        }
+    }
+    // Lossy counting keeps no candidates
+    if algorithm == Algorithm::Lossy
+        && (budget != Budget::default() || counting.is_some() || relabel.is_some())
+    {
+        return usage(String::from(
+            "--max-candidates, --max-memory, --counting and --relabel cannot be used with the \
+             lossy algorithm",
+        ));
+    }
+    if matches!(epsilon, Some(epsilon) if epsilon >= min_support) {
+        return usage(String::from("epsilon must be less than min support"));
+    }
+    // Negative rules need the counts of the infrequent candidates
+    if negative && (algorithm != Algorithm::Apriori || chunk_size.is_some()) {
+        return usage(String::from(
+            "--negative requires the apriori algorithm without --chunk-size",
+        ));
+    }
+    let basket = match (command, basket) {
+        (Command::Recommend, None) => return usage(String::from("recommend requires --basket")),
+        (_, basket) => basket
+            .map(|basket| {
+                basket
+                    .split(delimiter)
+                    .map(|item| String::from(item.trim()))
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    Ok(Some(Args {
+        command,
+        input: input.ok_or_else(|| CliError::Usage(String::from("missing INPUT")))?,
+        min_support,
+        min_confidence,
+        max_length,
+        algorithm,
+        epsilon,
+        sample_size,
+        seed,
+        budget,
+        chunk_size,
+        counting: counting.unwrap_or_default(),
+        relabel,
+        format,
+        output,
+        output_format,
+        negative,
+        basket,
+        top,
+        scoring,
+    }))
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("invalid value of {}: {:?}", name, value)))
+}
+
+fn read(args: &Args) -> io::Result<(Vec<Transaction>, Interner)> {
+    if args.input == "-" {
+        read_transactions_from(BufReader::new(io::stdin().lock()), &args.format)
+    } else {
+        read_transactions(&args.input, &args.format)
+    }
+}
+
+/// Mine the frequent itemsets of the input. Returns the item names and the
+/// no. of transactions as well.
+fn mine(args: &Args) -> Result<(FrequentItemsets, Interner, usize), CliError> {
+    let options = mining_options(args);
+    if let Some(chunk_size) = args.chunk_size {
+        let mut interner = Interner::new();
+        let (itemsets, N, exceeded) = generate_frequent_itemsets_from_file(
+            &args.input,
+            &args.format,
+            chunk_size,
+            &mut interner,
+            args.min_support,
+            args.max_length,
+            &options,
+        )?;
+        warn(exceeded);
+        return Ok((itemsets, interner, N));
+    }
+
+    let (transactions, interner) = read(args)?;
+    let N = transactions.len();
+    let itemsets = match args.algorithm {
+        Algorithm::Apriori => {
+            let (itemsets, exceeded) = generate_frequent_itemsets_from_transactions_with_options(
+                transactions,
+                args.min_support,
+                args.max_length,
+                &options,
+            )?;
+            warn(exceeded);
+            itemsets
+        }
+        Algorithm::Lossy => {
+            let epsilon = args.epsilon.unwrap_or(args.min_support / 10.0);
+            let mut counter = LossyCounter::new(epsilon, args.max_length)?;
+            counter.extend(&transactions);
+            counter.frequent_itemsets(args.min_support)?
+        }
+        Algorithm::Sample => {
+            let sampling = Sampling {
+                sample_size: args.sample_size.unwrap_or((N / 10).max(1)),
+                seed: args.seed.unwrap_or_else(rand::random),
+                lowered_support: None,
+            };
+            let (itemsets, exact) = generate_frequent_itemsets_sampled(
+                &transactions,
+                args.min_support,
+                args.max_length,
+                &sampling,
+                &options,
+            )?;
+            if !exact {
+                eprintln!(
+                    "apriori: warning: the sample missed frequent itemsets; mine again with a \
+                     larger --sample-size for all of them"
+                );
+            }
+            itemsets
+        }
+    };
+    Ok((itemsets, interner, N))
+}
+
+/// Mine the frequent itemsets of the input with apriori, along with the counts
+/// of the candidates found infrequent
+fn mine_with_infrequent(
+    args: &Args,
+) -> Result<(FrequentItemsets, FrequentItemsets, Interner, usize), CliError> {
+    let (transactions, interner) = read(args)?;
+    let N = transactions.len();
+    let (itemsets, infrequent, exceeded) =
+        generate_frequent_and_infrequent_itemsets_from_transactions_with_options(
+            transactions,
+            args.min_support,
+            args.max_length,
+            &mining_options(args),
+        )?;
+    warn(exceeded);
+    Ok((itemsets, infrequent, interner, N))
+}
+
+fn mining_options(args: &Args) -> MiningOptions<'static> {
+    let mut options = MiningOptions::default()
+        .with_budget(args.budget)
+        .with_counting(args.counting);
+    options.relabel = args.relabel;
+    options
+}
+
+fn warn(exceeded: Option<BudgetExceeded>) {
+    if let Some(exceeded) = exceeded {
+        eprintln!("apriori: warning: {}", exceeded);
+    }
+}
+
+fn write_stats<W: Write>(
+    mut writer: W,
+    transactions: &[Transaction],
+    interner: &Interner,
+) -> io::Result<()> {
+    let N = transactions.len();
+    let num_items: usize = transactions
+        .iter()
+        .map(|transaction| transaction.len())
+        .sum();
+    let max_length = transactions.iter().map(|t| t.len()).max().unwrap_or(0);
+    let mean_length = if N == 0 {
+        0.0
+    } else {
+        num_items as f64 / N as f64
+    };
+    let density = if interner.is_empty() {
+        0.0
+    } else {
+        mean_length / interner.len() as f64
+    };
+
+    writeln!(writer, "transactions: {}", N)?;
+    writeln!(writer, "items: {}", interner.len())?;
+    writeln!(writer, "mean transaction length: {:.3}", mean_length)?;
+    writeln!(writer, "max transaction length: {}", max_length)?;
+    writeln!(writer, "density: {:.6}", density)?;
+    writer.flush()
+}
+
+/// The output file, or standard output. Opened only once there is something
+/// to write, so that errors before leave an existing file untouched.
+fn writer(args: &Args) -> io::Result<Box<dyn Write>> {
+    Ok(match &args.output {
+        Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+fn run(args: Args) -> Result<(), CliError> {
+    match args.command {
+        Command::Stats => {
+            let (transactions, interner) = read(&args)?;
+            write_stats(writer(&args)?, &transactions, &interner)?
+        }
+        Command::Itemsets => {
+            let (itemsets, interner, N) = mine(&args)?;
+            write_itemsets(
+                writer(&args)?,
+                &itemsets,
+                &interner.inventory(),
+                N,
+                args.output_format,
+            )?;
+        }
+        Command::Rules => {
+            let (mut rules, interner, N) = if args.negative {
+                let (itemsets, infrequent, interner, N) = mine_with_infrequent(&args)?;
+                let rules =
+                    generate_negative_rules(&args.min_confidence, &itemsets, &infrequent, N)?;
+                (rules, interner, N)
+            } else {
+                let (itemsets, interner, N) = mine(&args)?;
+                let rules = generate_rules(&args.min_confidence, &itemsets, N)?;
+                (rules, interner, N)
+            };
+            sort_rules(&mut rules);
+            write_rules(
+                writer(&args)?,
+                &rules,
+                &interner.inventory(),
+                N,
+                args.output_format,
+            )?;
+        }
+        Command::Recommend => {
+            let (itemsets, interner, N) = mine(&args)?;
+            let recommender = Recommender::new(generate_rules(&args.min_confidence, &itemsets, N)?);
+            // Items that do not occur in the transactions cannot match a rule
+            let basket: Vec<usize> = args
+                .basket
+                .iter()
+                .filter_map(|item| interner.get(item))
+                .collect();
+            let recommendations = recommender.recommend(&basket, args.top, args.scoring);
+            let mut writer = writer(&args)?;
+            for (item_id, score) in recommendations {
+                writeln!(writer, "{}\t{}", interner.name(item_id).unwrap(), score)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("apriori: {}", e);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("apriori: {}", e);
+        process::exit(match e {
+            CliError::Usage(_) => 2,
+            _ => 1,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<Option<Args>, CliError> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = args("rules -s 0.2 --max-length 4 -f long -d ; --header -t csv data.csv")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(args.command, Command::Rules);
+        assert_eq!(args.input, "data.csv");
+        assert_eq!(args.min_support, 0.2);
+        assert_eq!(args.min_confidence, 0.5);
+        assert_eq!(args.max_length, 4);
+        assert_eq!(
+            args.format,
+            Format::Long {
+                delimiter: ';',
+                has_header: true
+            }
+        );
+        assert_eq!(args.output_format, OutputFormat::Csv);
+    }
+
+    #[test]
+    fn test_parse_basket() {
+        let args = args("recommend -b bread,milk -m lift -").unwrap().unwrap();
+
+        assert_eq!(args.input, "-");
+        assert_eq!(args.basket, vec!["bread", "milk"]);
+        assert_eq!(args.scoring, Scoring::SumLift);
+    }
+
+    #[test]
+    fn test_parse_budget() {
+        let args = args("itemsets --max-candidates 1000 data.csv")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(args.budget.max_candidates, Some(1000));
+        assert_eq!(args.budget.max_memory, None);
+    }
+
+    #[test]
+    fn test_parse_counting() {
+        let parsed = args("itemsets --counting scan --relabel ascending data.csv")
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.counting, Counting::Scan);
+        assert_eq!(parsed.relabel, Some(SupportOrder::Ascending));
+
+        assert!(matches!(
+            args("itemsets --counting hash data.csv"),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_lossy() {
+        let parsed = args("itemsets -a lossy -e 0.01 data.csv").unwrap().unwrap();
+        assert_eq!(parsed.algorithm, Algorithm::Lossy);
+        assert_eq!(parsed.epsilon, Some(0.01));
+
+        for options in &[
+            "--max-candidates 10",
+            "--max-memory 1000",
+            "--counting trie",
+            "--relabel descending",
+            "-s 0.05 -e 0.1",
+            "-s 0.1 -e 0.1",
+        ] {
+            assert!(matches!(
+                args(&format!("itemsets -a lossy {} data.csv", options)),
+                Err(CliError::Usage(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_sample() {
+        let args = args("itemsets -a sample --sample-size 500 --seed 7 data.csv")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(args.algorithm, Algorithm::Sample);
+        assert_eq!(args.sample_size, Some(500));
+        assert_eq!(args.seed, Some(7));
+    }
+
+    #[test]
+    fn test_parse_chunk_size() {
+        let parsed = args("itemsets --chunk-size 100000 data.txt")
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.chunk_size, Some(100000));
+
+        assert!(matches!(
+            args("itemsets -a sample --chunk-size 10 data.txt"),
+            Err(CliError::Usage(_))
+        ));
+
+        assert!(matches!(
+            args("itemsets --chunk-size 10 -"),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            args("itemsets --chunk-size 10 -f long data.csv"),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(args("mine data.csv"), Err(CliError::Usage(_))));
+        assert!(matches!(args("rules -s"), Err(CliError::Usage(_))));
+        assert!(matches!(
+            args("rules -s 2 data.csv"),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(args("rules"), Err(CliError::Usage(_))));
+        assert!(matches!(
+            args("recommend data.csv"),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            args("rules --negative -a lossy data.csv"),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(args("--help"), Ok(None)));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use crate::{
+    error::{Error, Result},
+    itemsets::count::generate_frequent_itemsets_id,
+    rules::{rule::Rule, search::generate_rules_with_consequents},
+    types::{ItemId, RawTransactionId, Transaction},
+};
+
+pub type ClassLabel = usize;
+
+/// Classification based on association rules (CBA, Liu et al.).
+///
+/// Class association rules, i.e. rules whose consequent is a single class
+/// label, are mined from the training transactions, ranked and pruned by
+/// database coverage. A transaction is assigned the class of the first rule
+/// whose antecedent it contains, or the default class if there is none.
+pub struct Classifier {
+    /// Class label of each class item
+    classes: HashMap<ItemId, ClassLabel>,
+    /// Selected rules, in order of precedence
+    rules: Vec<Rule>,
+    default_class: ClassLabel,
+}
+
+impl Classifier {
+    /// Mine and select class association rules from `transactions`, where
+    /// `labels[i]` is the class label of `transactions[i]`.
+    pub fn fit(
+        transactions: &[Transaction],
+        labels: &[ClassLabel],
+        min_support: f32,
+        min_conf: f32,
+        max_length: usize,
+    ) -> Result<Classifier> {
+        if transactions.len() != labels.len() {
+            return Err(Error::InvalidParameter(String::from(
+                "expected one label per transaction",
+            )));
+        }
+        if transactions.is_empty() {
+            return Err(Error::InvalidParameter(String::from(
+                "expected at least one transaction",
+            )));
+        }
+
+        // Class labels are added to the transactions as items that do not
+        // clash with the existing ones
+        let offset = transactions.iter().flatten().max().map_or(0, |&i| i + 1);
+        let classes: HashMap<ItemId, ClassLabel> = labels
+            .iter()
+            .map(|&label| (offset + label, label))
+            .collect();
+        let raw_transactions: Vec<RawTransactionId> = transactions
+            .iter()
+            .zip(labels)
+            .map(|(transaction, &label)| {
+                let mut raw_transaction: RawTransactionId = transaction.iter().copied().collect();
+                raw_transaction.insert(offset + label);
+                raw_transaction
+            })
+            .collect();
+
+        let itemset_counts =
+            generate_frequent_itemsets_id(raw_transactions, min_support, max_length)?;
+        let class_items: HashSet<ItemId> = classes.keys().copied().collect();
+        let mut rules = generate_rules_with_consequents(
+            &min_conf,
+            &itemset_counts,
+            transactions.len(),
+            &class_items,
+        )?;
+        rules.sort_by(precedence);
+
+        let (rules, default_class) =
+            prune_by_database_coverage(rules, &classes, transactions, labels);
+
+        Ok(Classifier {
+            classes,
+            rules,
+            default_class,
+        })
+    }
+
+    pub fn predict(&self, transaction: &[ItemId]) -> ClassLabel {
+        let transaction: HashSet<ItemId> = transaction.iter().copied().collect();
+        self.rules
+            .iter()
+            .find(|rule| covers(rule, &transaction))
+            .map_or(self.default_class, |rule| self.class_of(rule))
+    }
+
+    /// Fraction of transactions whose predicted class is the given label
+    pub fn accuracy(&self, transactions: &[Transaction], labels: &[ClassLabel]) -> f32 {
+        if transactions.is_empty() {
+            return 0.0;
+        }
+        let num_correct = transactions
+            .iter()
+            .zip(labels)
+            .filter(|(transaction, &label)| self.predict(transaction) == label)
+            .count();
+        num_correct as f32 / transactions.len() as f32
+    }
+
+    /// Selected rules, in order of precedence. The consequent of every rule is
+    /// a class item; see `class_of`.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn class_of(&self, rule: &Rule) -> ClassLabel {
+        self.classes[&rule.get_consequent()[0]]
+    }
+
+    pub fn default_class(&self) -> ClassLabel {
+        self.default_class
+    }
+}
+
+/// Higher confidence first, then higher support, then shorter antecedent
+fn precedence(a: &Rule, b: &Rule) -> Ordering {
+    b.confidence
+        .total_cmp(&a.confidence)
+        .then(b.support.total_cmp(&a.support))
+        .then(a.get_antecedent().len().cmp(&b.get_antecedent().len()))
+        .then_with(|| a.get_antecedent().cmp(b.get_antecedent()))
+        .then_with(|| a.get_consequent().cmp(b.get_consequent()))
+}
+
+fn covers(rule: &Rule, transaction: &HashSet<ItemId>) -> bool {
+    rule.get_antecedent()
+        .iter()
+        .all(|item| transaction.contains(item))
+}
+
+fn majority_class(labels: impl Iterator<Item = ClassLabel>) -> Option<ClassLabel> {
+    let mut counts: HashMap<ClassLabel, usize> = HashMap::new();
+    for label in labels {
+        *counts.entry(label).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(label, _)| label)
+}
+
+/// CBA-CB (M1): keep a rule if it correctly classifies at least one of the
+/// transactions not covered by the rules before it, then truncate the rules
+/// where the total no. of errors, including those of the default class, is
+/// lowest.
+fn prune_by_database_coverage(
+    rules: Vec<Rule>,
+    classes: &HashMap<ItemId, ClassLabel>,
+    transactions: &[Transaction],
+    labels: &[ClassLabel],
+) -> (Vec<Rule>, ClassLabel) {
+    let overall_majority = majority_class(labels.iter().copied()).unwrap_or(0);
+    let mut remaining: Vec<(HashSet<ItemId>, ClassLabel)> = transactions
+        .iter()
+        .zip(labels)
+        .map(|(transaction, &label)| (transaction.iter().copied().collect(), label))
+        .collect();
+
+    let default_errors = |remaining: &[(HashSet<ItemId>, ClassLabel)]| {
+        let default_class =
+            majority_class(remaining.iter().map(|(_, label)| *label)).unwrap_or(overall_majority);
+        let errors = remaining
+            .iter()
+            .filter(|(_, label)| *label != default_class)
+            .count();
+        (default_class, errors)
+    };
+
+    let (default_class, errors) = default_errors(&remaining);
+    let mut best = (errors, 0, default_class);
+    let mut selected = vec![];
+    let mut rule_errors = 0;
+
+    for rule in rules {
+        if remaining.is_empty() {
+            break;
+        }
+        let class = classes[&rule.get_consequent()[0]];
+        let (covered, uncovered): (Vec<_>, Vec<_>) = remaining
+            .into_iter()
+            .partition(|(transaction, _)| covers(&rule, transaction));
+        remaining = uncovered;
+
+        if !covered.iter().any(|(_, label)| *label == class) {
+            remaining.extend(covered);
+            continue;
+        }
+
+        rule_errors += covered.iter().filter(|(_, label)| *label != class).count();
+        selected.push(rule);
+
+        let (default_class, errors) = default_errors(&remaining);
+        if rule_errors + errors < best.0 {
+            best = (rule_errors + errors, selected.len(), default_class);
+        }
+    }
+
+    let (_, num_rules, default_class) = best;
+    selected.truncate(num_rules);
+    (selected, default_class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: ItemId = 0;
+    const B: ItemId = 1;
+    const C: ItemId = 2;
+    const D: ItemId = 3;
+
+    const YES: ClassLabel = 0;
+    const NO: ClassLabel = 1;
+
+    fn data() -> (Vec<Transaction>, Vec<ClassLabel>) {
+        let transactions = vec![
+            vec![A, B],
+            vec![A, C],
+            vec![A, B, C],
+            vec![A, D],
+            vec![B, D],
+            vec![C, D],
+            vec![B, C, D],
+            vec![D],
+        ];
+        let labels = vec![YES, YES, YES, YES, NO, NO, NO, NO];
+        (transactions, labels)
+    }
+
+    #[test]
+    fn test_fit_predict() {
+        let (transactions, labels) = data();
+        let classifier = Classifier::fit(&transactions, &labels, 0.2, 0.8, 3).unwrap();
+
+        assert_eq!(classifier.predict(&[A]), YES);
+        assert_eq!(classifier.predict(&[B, D]), NO);
+        assert_eq!(classifier.accuracy(&transactions, &labels), 1.0);
+        assert!(classifier
+            .rules()
+            .iter()
+            .all(|rule| rule.get_antecedent().iter().all(|item| *item <= D)));
+    }
+
+    #[test]
+    fn test_rules_are_ranked() {
+        let (transactions, labels) = data();
+        let classifier = Classifier::fit(&transactions, &labels, 0.1, 0.5, 3).unwrap();
+
+        for pair in classifier.rules().windows(2) {
+            assert_ne!(precedence(&pair[0], &pair[1]), Ordering::Greater);
+        }
+    }
+
+    #[test]
+    fn test_default_class() {
+        let transactions = vec![vec![A], vec![B], vec![C]];
+        let labels = vec![NO, NO, YES];
+        let classifier = Classifier::fit(&transactions, &labels, 0.9, 0.9, 2).unwrap();
+
+        assert!(classifier.rules().is_empty());
+        assert_eq!(classifier.default_class(), NO);
+        assert_eq!(classifier.predict(&[D]), NO);
+    }
+
+    #[test]
+    fn test_rejects_empty() {
+        assert!(matches!(
+            Classifier::fit(&[], &[], 0.1, 0.5, 3),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
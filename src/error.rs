@@ -0,0 +1,80 @@
+use std::{fmt, io};
+
+use crate::{
+    model::VERSION,
+    types::{Itemset, ItemsetLength},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// A parameter out of range, or an input that cannot be mined
+    InvalidParameter(String),
+    /// The count of an itemset needed to compute a rule is missing
+    MissingCount(Itemset),
+    Io(io::Error),
+    /// The run was stopped through its `CancellationToken`
+    Cancelled,
+    /// Not a model, or a corrupted one
+    Format(String),
+    /// A model written by another version of this library
+    Version(u16),
+    /// A model whose contents are inconsistent
+    InvalidModel(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidParameter(e) => write!(f, "{}", e),
+            Error::MissingCount(itemset) => write!(f, "missing count of itemset {:?}", itemset),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Cancelled => write!(f, "mining was cancelled"),
+            Error::Format(e) => write!(f, "invalid model format: {}", e),
+            Error::Version(version) => write!(
+                f,
+                "unsupported model version {} (expected {})",
+                version, VERSION
+            ),
+            Error::InvalidModel(e) => write!(f, "invalid model: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Check that a support, confidence or similar is within [0, 1]
+pub(crate) fn check_fraction(name: &str, value: f32) -> Result<()> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::InvalidParameter(format!(
+            "{} must be between 0 and 1, got {}",
+            name, value
+        )))
+    }
+}
+
+pub(crate) fn check_max_length(max_length: ItemsetLength) -> Result<()> {
+    if max_length >= 1 {
+        Ok(())
+    } else {
+        Err(Error::InvalidParameter(String::from(
+            "max_length must be at least 1",
+        )))
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use crate::types::{Inventory, ItemId, OwnedInventory};
+
+/// Assigns item IDs to item names in order of first appearance, for data that
+/// outlives the Python objects (or files) the names came from.
+#[derive(Default)]
+pub struct Interner {
+    ids: HashMap<String, ItemId>,
+    names: OwnedInventory,
+    next_id: ItemId,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> ItemId {
+        if let Some(&item_id) = self.ids.get(name) {
+            return item_id;
+        }
+        let item_id = self.next_id;
+        self.next_id += 1;
+        self.ids.insert(String::from(name), item_id);
+        self.names.insert(item_id, String::from(name));
+        item_id
+    }
+
+    pub fn get(&self, name: &str) -> Option<ItemId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, item_id: ItemId) -> Option<&str> {
+        self.names.get(&item_id).map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn inventory(&self) -> Inventory<'_> {
+        self.names
+            .iter()
+            .map(|(&item_id, name)| (item_id, name.as_str()))
+            .collect()
+    }
+
+    pub fn names(&self) -> &OwnedInventory {
+        &self.names
+    }
+
+    pub fn into_inventory(self) -> OwnedInventory {
+        self.names
+    }
+}
+
+impl From<OwnedInventory> for Interner {
+    fn from(names: OwnedInventory) -> Self {
+        let ids = names
+            .iter()
+            .map(|(&item_id, name)| (name.clone(), item_id))
+            .collect();
+        let next_id = names.keys().max().map_or(0, |&item_id| item_id + 1);
+        Self {
+            ids,
+            names,
+            next_id,
+        }
+    }
+}
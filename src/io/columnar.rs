@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, Result},
+    rules::rule::{itemset_count, support_count, Rule},
+    types::{FrequentItemsets, Inventory, ItemId, Itemset},
+};
+
+/// Item names, and the index of each item ID among them
+pub struct Dictionary<'l> {
+    pub names: Vec<&'l str>,
+    pub indices: HashMap<ItemId, i32>,
+}
+
+impl<'l> Dictionary<'l> {
+    /// Names in order of item ID
+    pub fn new(inventory: &Inventory<'l>) -> Self {
+        let mut items: Vec<(ItemId, &str)> = inventory
+            .iter()
+            .map(|(&item_id, &name)| (item_id, name))
+            .collect();
+        items.sort_unstable();
+        let indices = items
+            .iter()
+            .enumerate()
+            .map(|(i, &(item_id, _))| (item_id, i as i32))
+            .collect();
+        let names = items.into_iter().map(|(_, name)| name).collect();
+        Dictionary { names, indices }
+    }
+}
+
+/// A column of lists of items, in the Arrow layout: the items of row `i` are
+/// `items[offsets[i]..offsets[i + 1]]`, as indices into a `Dictionary`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ItemListColumn {
+    pub offsets: Vec<i32>,
+    pub items: Vec<i32>,
+}
+
+impl ItemListColumn {
+    fn new() -> Self {
+        ItemListColumn {
+            offsets: vec![0],
+            items: vec![],
+        }
+    }
+
+    fn push(&mut self, items: &[ItemId], dictionary: &Dictionary) {
+        self.items
+            .extend(items.iter().map(|item_id| dictionary.indices[item_id]));
+        self.offsets.push(self.items.len() as i32);
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Frequent itemsets as columns, by size, then by item IDs.
+#[derive(Debug, PartialEq)]
+pub struct ItemsetColumns {
+    pub itemset: ItemListColumn,
+    pub support_count: Vec<u32>,
+    pub support: Vec<f32>,
+}
+
+impl ItemsetColumns {
+    pub fn new(itemsets: &FrequentItemsets, dictionary: &Dictionary, N: usize) -> Self {
+        let mut itemset_counts: Vec<(&Itemset, u32)> = itemsets
+            .values()
+            .flatten()
+            .map(|(itemset, &count)| (itemset, count))
+            .collect();
+        itemset_counts.sort_unstable_by(|a, b| a.0.len().cmp(&b.0.len()).then(a.0.cmp(b.0)));
+
+        let mut columns = ItemsetColumns {
+            itemset: ItemListColumn::new(),
+            support_count: Vec::with_capacity(itemset_counts.len()),
+            support: Vec::with_capacity(itemset_counts.len()),
+        };
+        for (itemset, count) in itemset_counts {
+            columns.itemset.push(itemset, dictionary);
+            columns.support_count.push(count);
+            columns.support.push(count as f32 / N as f32);
+        }
+        columns
+    }
+}
+
+/// Rules as columns, in the given order. Besides the rules' own metrics, the
+/// support counts of the rules and the supports of both sides are computed
+/// from the counts of the itemsets, as for the rules, and leverage and
+/// conviction derived from them.
+#[derive(Debug, PartialEq)]
+pub struct RuleColumns {
+    pub antecedent: ItemListColumn,
+    pub consequent: ItemListColumn,
+    pub support_count: Vec<u32>,
+    pub support: Vec<f32>,
+    pub confidence: Vec<f32>,
+    pub lift: Vec<f32>,
+    pub antecedent_support: Vec<f32>,
+    pub consequent_support: Vec<f32>,
+    pub leverage: Vec<f32>,
+    pub conviction: Vec<f32>,
+    pub negated_antecedent: Vec<bool>,
+    pub negated_consequent: Vec<bool>,
+}
+
+impl RuleColumns {
+    pub fn new(
+        rules: &[Rule],
+        itemsets: &FrequentItemsets,
+        dictionary: &Dictionary,
+        N: usize,
+    ) -> Result<Self> {
+        let n = rules.len();
+        let mut columns = RuleColumns {
+            antecedent: ItemListColumn::new(),
+            consequent: ItemListColumn::new(),
+            support_count: Vec::with_capacity(n),
+            support: Vec::with_capacity(n),
+            confidence: Vec::with_capacity(n),
+            lift: Vec::with_capacity(n),
+            antecedent_support: Vec::with_capacity(n),
+            consequent_support: Vec::with_capacity(n),
+            leverage: Vec::with_capacity(n),
+            conviction: Vec::with_capacity(n),
+            negated_antecedent: Vec::with_capacity(n),
+            negated_consequent: Vec::with_capacity(n),
+        };
+        // The support of a negated side is that of its complement
+        let side_support = |itemset: &[ItemId], negated: bool| -> Result<f32> {
+            let support = support_count(itemsets, itemset)? / N as f32;
+            Ok(if negated { 1.0 - support } else { support })
+        };
+        // A negated side occurs whenever the other side occurs without it
+        let rule_support_count = |rule: &Rule| -> Result<u32> {
+            let union_count = itemset_count(itemsets, &rule.combi)?;
+            match (rule.negated_antecedent, rule.negated_consequent) {
+                (false, false) => Ok(union_count),
+                (false, true) => {
+                    Ok(itemset_count(itemsets, rule.get_antecedent())?.saturating_sub(union_count))
+                }
+                (true, false) => {
+                    Ok(itemset_count(itemsets, rule.get_consequent())?.saturating_sub(union_count))
+                }
+                (true, true) => Err(Error::InvalidParameter(String::from(
+                    "negative rule must negate either its antecedent or its consequent",
+                ))),
+            }
+        };
+        for rule in rules {
+            let antecedent_support = side_support(rule.get_antecedent(), rule.negated_antecedent)?;
+            let consequent_support = side_support(rule.get_consequent(), rule.negated_consequent)?;
+
+            columns.antecedent.push(rule.get_antecedent(), dictionary);
+            columns.consequent.push(rule.get_consequent(), dictionary);
+            columns.support_count.push(rule_support_count(rule)?);
+            columns.support.push(rule.support);
+            columns.confidence.push(rule.confidence);
+            columns.lift.push(rule.lift);
+            columns.antecedent_support.push(antecedent_support);
+            columns.consequent_support.push(consequent_support);
+            columns
+                .leverage
+                .push(rule.support - antecedent_support * consequent_support);
+            // Conviction is infinite for rules that always hold
+            columns.conviction.push(if rule.confidence < 1.0 {
+                (1.0 - consequent_support) / (1.0 - rule.confidence)
+            } else {
+                f32::INFINITY
+            });
+            columns.negated_antecedent.push(rule.negated_antecedent);
+            columns.negated_consequent.push(rule.negated_consequent);
+        }
+        Ok(columns)
+    }
+}
+
+/// Pack booleans into an Arrow validity-style bitmap (least significant bit
+/// first)
+pub fn pack_bits(values: &[bool]) -> Vec<u8> {
+    let mut bitmap = vec![0u8; (values.len() + 7) / 8];
+    for (i, _) in values.iter().enumerate().filter(|(_, &value)| value) {
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+    bitmap
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Fixed-width numbers that Arrow stores as their little-endian bytes
+pub trait Primitive: sealed::Sealed + Copy {
+    fn extend_bytes(self, bytes: &mut Vec<u8>);
+}
+
+macro_rules! primitive {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl Primitive for $t {
+                fn extend_bytes(self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+primitive!(u8, i32, u32, u64, f32, f64);
+
+/// The bytes of an Arrow buffer holding `values`
+pub fn to_bytes<T: Primitive>(values: &[T]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(std::mem::size_of_val(values));
+    for &value in values {
+        value.extend_bytes(&mut bytes);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory() -> Inventory<'static> {
+        vec![(5, "milk"), (2, "bread"), (9, "cheese")]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_itemset_columns() {
+        let inventory = inventory();
+        let dictionary = Dictionary::new(&inventory);
+        assert_eq!(dictionary.names, vec!["bread", "milk", "cheese"]);
+
+        let mut itemsets = FrequentItemsets::new();
+        itemsets.insert(1, vec![(vec![5], 4), (vec![2], 3)].into_iter().collect());
+        itemsets.insert(2, vec![(vec![2, 9], 2)].into_iter().collect());
+        let columns = ItemsetColumns::new(&itemsets, &dictionary, 4);
+
+        assert_eq!(columns.itemset.offsets, vec![0, 1, 2, 4]);
+        assert_eq!(columns.itemset.items, vec![0, 1, 0, 2]);
+        assert_eq!(columns.support_count, vec![3, 4, 2]);
+        assert_eq!(columns.support, vec![0.75, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_rule_columns() {
+        let inventory = inventory();
+        let dictionary = Dictionary::new(&inventory);
+
+        // P(bread) = 0.5, P(milk) = 0.8, P(bread ∧ milk) = 0.4
+        let mut itemsets = FrequentItemsets::new();
+        itemsets.insert(1, vec![(vec![2], 5), (vec![5], 8)].into_iter().collect());
+        itemsets.insert(2, vec![(vec![2, 5], 4)].into_iter().collect());
+        let mut rule = Rule::from_split(&[2], &[5], false, false);
+        rule.compute_confidence(&itemsets, &[2, 5], 10.0).unwrap();
+        let columns = RuleColumns::new(&[rule], &itemsets, &dictionary, 10).unwrap();
+
+        assert_eq!(columns.antecedent.items, vec![0]);
+        assert_eq!(columns.consequent.items, vec![1]);
+        assert_eq!(columns.support_count, vec![4]);
+        assert!((columns.antecedent_support[0] - 0.5).abs() < 1e-6);
+        assert!((columns.consequent_support[0] - 0.8).abs() < 1e-6);
+        assert!(columns.leverage[0].abs() < 1e-6);
+        assert!((columns.conviction[0] - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_rule_columns_zero_confidence() {
+        let inventory = inventory();
+        let dictionary = Dictionary::new(&inventory);
+
+        // P(bread) = 0.5, P(milk) = 0.8, bread never occurs without milk, so
+        // bread -> ¬milk has zero confidence and lift
+        let mut itemsets = FrequentItemsets::new();
+        itemsets.insert(1, vec![(vec![2], 5), (vec![5], 8)].into_iter().collect());
+        itemsets.insert(2, vec![(vec![2, 5], 5)].into_iter().collect());
+        let mut rule = Rule::from_split(&[2], &[5], false, true);
+        rule.compute_negative_confidence(&itemsets, &[2, 5], 10.0)
+            .unwrap();
+        assert_eq!(rule.confidence, 0.0);
+        let columns = RuleColumns::new(&[rule], &itemsets, &dictionary, 10).unwrap();
+
+        assert_eq!(columns.support_count, vec![0]);
+        assert!((columns.antecedent_support[0] - 0.5).abs() < 1e-6);
+        assert!((columns.consequent_support[0] - 0.2).abs() < 1e-6);
+        assert!((columns.leverage[0] + 0.1).abs() < 1e-6);
+        assert!((columns.conviction[0] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rule_columns_negated_antecedent() {
+        let inventory = inventory();
+        let dictionary = Dictionary::new(&inventory);
+
+        // ¬bread -> milk holds in 8 - 4 of 10 transactions
+        let mut itemsets = FrequentItemsets::new();
+        itemsets.insert(1, vec![(vec![2], 5), (vec![5], 8)].into_iter().collect());
+        itemsets.insert(2, vec![(vec![2, 5], 4)].into_iter().collect());
+        let mut rule = Rule::from_split(&[2], &[5], true, false);
+        rule.compute_negative_confidence(&itemsets, &[2, 5], 10.0)
+            .unwrap();
+        let columns = RuleColumns::new(&[rule], &itemsets, &dictionary, 10).unwrap();
+
+        assert_eq!(columns.support_count, vec![4]);
+        assert!((columns.support[0] - 0.4).abs() < 1e-6);
+        assert!((columns.antecedent_support[0] - 0.5).abs() < 1e-6);
+        assert!((columns.consequent_support[0] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pack_bits() {
+        let values = [true, false, false, true, false, false, false, false, true];
+        assert_eq!(pack_bits(&values), vec![0b0000_1001, 0b0000_0001]);
+        assert!(pack_bits(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        assert_eq!(to_bytes(&[1i32, -1]), vec![1, 0, 0, 0, 255, 255, 255, 255]);
+        assert_eq!(to_bytes(&[1.0f32]), vec![0, 0, 128, 63]);
+        assert!(to_bytes::<u64>(&[]).is_empty());
+    }
+}
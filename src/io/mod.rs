@@ -0,0 +1,4 @@
+pub mod columnar;
+pub mod pmml;
+pub mod read;
+pub mod write;
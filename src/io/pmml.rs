@@ -0,0 +1,418 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::{
+    error::{Error, Result},
+    model::{Model, Parameters, VERSION},
+    rules::rule::Rule,
+    types::{FrequentItemsets, ItemId, Itemset, OwnedInventory},
+};
+
+const PMML_VERSION: &str = "4.4";
+const PMML_NAMESPACE: &str = "http://www.dmg.org/PMML-4_4";
+
+/// Write a model as a PMML `AssociationModel`. Item IDs are used as PMML item
+/// IDs, and itemsets are numbered from 1 by size, then by item IDs.
+///
+/// PMML cannot represent negative rules, so these are rejected.
+pub fn write_pmml<W: Write>(mut writer: W, model: &Model) -> io::Result<()> {
+    if model.rules.iter().any(|rule| rule.is_negative()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "negative rules cannot be written as PMML",
+        ));
+    }
+
+    let N = model.num_transactions as f32;
+    let mut itemsets: Vec<(&Itemset, Option<f32>)> = model
+        .itemsets
+        .values()
+        .flatten()
+        .map(|(itemset, &count)| (itemset, Some(count as f32 / N)))
+        .collect();
+    itemsets.sort_unstable_by(|a, b| a.0.len().cmp(&b.0.len()).then(a.0.cmp(b.0)));
+
+    // The sides of a rule are frequent, but may not have been mined if the
+    // rules came from elsewhere
+    let mut sides: Vec<Itemset> = model
+        .rules
+        .iter()
+        .flat_map(|rule| {
+            [
+                rule.get_antecedent().to_vec(),
+                rule.get_consequent().to_vec(),
+            ]
+        })
+        .collect();
+    sides.sort_unstable();
+    sides.dedup();
+    let mut ids: HashMap<&[ItemId], usize> = itemsets
+        .iter()
+        .enumerate()
+        .map(|(i, (itemset, _))| (itemset.as_slice(), i + 1))
+        .collect();
+    for side in &sides {
+        if !ids.contains_key(side.as_slice()) {
+            ids.insert(side, itemsets.len() + 1);
+            itemsets.push((side, None));
+        }
+    }
+
+    let mut items: Vec<(&ItemId, &String)> = model.inventory.iter().collect();
+    items.sort_unstable();
+    let parameters = &model.parameters;
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<PMML version="{}" xmlns="{}">"#,
+        PMML_VERSION, PMML_NAMESPACE
+    )?;
+    writeln!(
+        writer,
+        r#"  <Header><Application name="{}" version="{}"/></Header>"#,
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(writer, r#"  <DataDictionary numberOfFields="2">"#)?;
+    writeln!(
+        writer,
+        r#"    <DataField name="transaction" optype="categorical" dataType="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"    <DataField name="item" optype="categorical" dataType="string"/>"#
+    )?;
+    writeln!(writer, "  </DataDictionary>")?;
+    writeln!(
+        writer,
+        concat!(
+            r#"  <AssociationModel functionName="associationRules" algorithmName="apriori""#,
+            r#" numberOfTransactions="{}" minimumSupport="{}" minimumConfidence="{}""#,
+            r#" lengthLimit="{}" numberOfItems="{}" numberOfItemsets="{}" numberOfRules="{}">"#
+        ),
+        model.num_transactions,
+        parameters.min_support,
+        parameters.min_confidence,
+        parameters.max_length,
+        items.len(),
+        itemsets.len(),
+        model.rules.len()
+    )?;
+    writeln!(writer, "    <MiningSchema>")?;
+    writeln!(
+        writer,
+        r#"      <MiningField name="transaction" usageType="group"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"      <MiningField name="item" usageType="active"/>"#
+    )?;
+    writeln!(writer, "    </MiningSchema>")?;
+
+    for (item_id, name) in items {
+        writeln!(
+            writer,
+            r#"    <Item id="{}" value="{}"/>"#,
+            item_id,
+            escape(name)
+        )?;
+    }
+    for (i, (itemset, support)) in itemsets.iter().enumerate() {
+        match support {
+            Some(support) => write!(
+                writer,
+                r#"    <Itemset id="{}" support="{}" numberOfItems="{}">"#,
+                i + 1,
+                support,
+                itemset.len()
+            )?,
+            None => write!(
+                writer,
+                r#"    <Itemset id="{}" numberOfItems="{}">"#,
+                i + 1,
+                itemset.len()
+            )?,
+        }
+        for item_id in itemset.iter() {
+            write!(writer, r#"<ItemRef itemRef="{}"/>"#, item_id)?;
+        }
+        writeln!(writer, "</Itemset>")?;
+    }
+    for rule in &model.rules {
+        writeln!(
+            writer,
+            r#"    <AssociationRule support="{}" confidence="{}" lift="{}" antecedent="{}" consequent="{}"/>"#,
+            rule.support,
+            rule.confidence,
+            rule.lift,
+            ids[rule.get_antecedent()],
+            ids[rule.get_consequent()]
+        )?;
+    }
+
+    writeln!(writer, "  </AssociationModel>")?;
+    writeln!(writer, "</PMML>")?;
+    writer.flush()
+}
+
+/// Read a PMML `AssociationModel` into a model. Items are assigned IDs in
+/// order of appearance. Itemsets without a support, and rules over itemsets
+/// that are not listed, are only used to resolve rules.
+pub fn read_pmml(text: &str) -> Result<Model> {
+    let document = roxmltree::Document::parse(text).map_err(|e| Error::Format(e.to_string()))?;
+    let association_model = document
+        .descendants()
+        .find(|node| node.has_tag_name("AssociationModel"))
+        .ok_or_else(|| format_error("missing AssociationModel"))?;
+
+    let num_transactions: usize = required(&association_model, "numberOfTransactions")?;
+    let min_support: f32 = required(&association_model, "minimumSupport")?;
+    let min_confidence: f32 = required(&association_model, "minimumConfidence")?;
+    let length_limit: Option<usize> = optional(&association_model, "lengthLimit")?;
+    let N = num_transactions as f32;
+
+    let mut inventory = OwnedInventory::new();
+    let mut item_ids: HashMap<&str, ItemId> = HashMap::new();
+    for item in children(&association_model, "Item") {
+        let id = attribute(&item, "id")?;
+        let value = attribute(&item, "value")?;
+        let item_id = inventory.len();
+        item_ids.insert(id, item_id);
+        inventory.insert(item_id, String::from(value));
+    }
+
+    let mut itemsets: FrequentItemsets = HashMap::new();
+    let mut sides: HashMap<&str, (Itemset, Option<f32>)> = HashMap::new();
+    for itemset in children(&association_model, "Itemset") {
+        let mut items = children(&itemset, "ItemRef")
+            .map(|item_ref| {
+                let item_ref = attribute(&item_ref, "itemRef")?;
+                item_ids
+                    .get(item_ref)
+                    .copied()
+                    .ok_or_else(|| invalid(format!("unknown item {}", item_ref)))
+            })
+            .collect::<Result<Itemset>>()?;
+        items.sort_unstable();
+        let support: Option<f32> = optional(&itemset, "support")?;
+        if let Some(support) = support {
+            itemsets
+                .entry(items.len())
+                .or_default()
+                .insert(items.clone(), (support * N).round() as u32);
+        }
+        sides.insert(attribute(&itemset, "id")?, (items, support));
+    }
+
+    let mut rules = vec![];
+    for association_rule in children(&association_model, "AssociationRule") {
+        let side = |name: &str| -> Result<&(Itemset, Option<f32>)> {
+            let id = attribute(&association_rule, name)?;
+            sides
+                .get(id)
+                .ok_or_else(|| invalid(format!("unknown itemset {}", id)))
+        };
+        let (antecedent, _) = side("antecedent")?;
+        let (consequent, consequent_support) = side("consequent")?;
+
+        let mut rule = Rule::from_split(antecedent, consequent, false, false);
+        rule.support = required(&association_rule, "support")?;
+        rule.confidence = required(&association_rule, "confidence")?;
+        rule.lift = match optional(&association_rule, "lift")? {
+            Some(lift) => lift,
+            None => consequent_support.map_or(0.0, |support| rule.confidence / support),
+        };
+
+        let mut itemset = rule.combi.clone();
+        itemset.sort_unstable();
+        itemsets
+            .entry(itemset.len())
+            .or_default()
+            .entry(itemset)
+            .or_insert((rule.support * N).round() as u32);
+        rules.push(rule);
+    }
+
+    let max_length = length_limit
+        .or_else(|| itemsets.keys().max().copied())
+        .unwrap_or(1);
+    let model = Model {
+        version: VERSION,
+        parameters: Parameters {
+            min_support,
+            min_confidence,
+            max_length,
+        },
+        num_transactions,
+        inventory,
+        itemsets,
+        rules,
+    };
+    model.validate()?;
+    Ok(model)
+}
+
+fn children<'a, 'input: 'a>(
+    node: &roxmltree::Node<'a, 'input>,
+    tag_name: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.has_tag_name(tag_name))
+}
+
+fn attribute<'a>(node: &roxmltree::Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name).ok_or_else(|| {
+        format_error(&format!(
+            "missing attribute {} of {}",
+            name,
+            node.tag_name().name()
+        ))
+    })
+}
+
+fn optional<T: std::str::FromStr>(node: &roxmltree::Node, name: &str) -> Result<Option<T>> {
+    node.attribute(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format_error(&format!("invalid {} {:?}", name, value)))
+        })
+        .transpose()
+}
+
+fn required<T: std::str::FromStr>(node: &roxmltree::Node, name: &str) -> Result<T> {
+    optional(node, name)?.ok_or_else(|| {
+        format_error(&format!(
+            "missing attribute {} of {}",
+            name,
+            node.tag_name().name()
+        ))
+    })
+}
+
+fn format_error(message: &str) -> Error {
+    Error::Format(String::from(message))
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidModel(message)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RawTransaction;
+    use std::collections::HashSet;
+
+    fn model() -> Model {
+        let transactions: Vec<RawTransaction> = vec![
+            vec!["bread", "milk", "cheese & co"],
+            vec!["bread", "milk"],
+            vec!["milk", "cheese & co"],
+            vec!["bread", "cheese & co", "milk"],
+        ]
+        .into_iter()
+        .map(|items| items.into_iter().collect::<HashSet<_>>())
+        .collect();
+        Model::fit(transactions, 0.5, 0.5, 3).unwrap()
+    }
+
+    fn to_pmml(model: &Model) -> String {
+        let mut buffer = vec![];
+        write_pmml(&mut buffer, model).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    /// Rules as (antecedent names, consequent names, support, confidence)
+    fn named_rules(model: &Model) -> Vec<(Vec<String>, Vec<String>, f32, f32)> {
+        let names = |items: &[ItemId]| {
+            let mut names: Vec<String> = items
+                .iter()
+                .map(|item_id| model.inventory[item_id].clone())
+                .collect();
+            names.sort();
+            names
+        };
+        let mut rules: Vec<_> = model
+            .rules
+            .iter()
+            .map(|rule| {
+                (
+                    names(rule.get_antecedent()),
+                    names(rule.get_consequent()),
+                    rule.support,
+                    rule.confidence,
+                )
+            })
+            .collect();
+        rules.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        rules
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let model = model();
+        let pmml = to_pmml(&model);
+        assert!(pmml.contains(r#"value="cheese &amp; co""#));
+
+        let loaded = read_pmml(&pmml).unwrap();
+        assert_eq!(loaded.parameters, model.parameters);
+        assert_eq!(loaded.num_transactions, model.num_transactions);
+        assert_eq!(loaded.itemsets, model.itemsets);
+        assert_eq!(named_rules(&loaded), named_rules(&model));
+    }
+
+    #[test]
+    fn test_read_minimal() {
+        let pmml = r#"<PMML version="4.1" xmlns="http://www.dmg.org/PMML-4_1">
+            <AssociationModel functionName="associationRules" numberOfTransactions="10"
+                    minimumSupport="0.2" minimumConfidence="0.5" numberOfItems="2"
+                    numberOfItemsets="2" numberOfRules="1">
+                <MiningSchema/>
+                <Item id="a" value="tea"/>
+                <Item id="b" value="sugar"/>
+                <Itemset id="1"><ItemRef itemRef="a"/></Itemset>
+                <Itemset id="2" support="0.4"><ItemRef itemRef="b"/></Itemset>
+                <AssociationRule support="0.3" confidence="0.6" antecedent="1" consequent="2"/>
+            </AssociationModel>
+        </PMML>"#;
+        let model = read_pmml(pmml).unwrap();
+
+        assert_eq!(model.inventory[&0], "tea");
+        assert_eq!(model.rules.len(), 1);
+        assert_eq!(model.rules[0].get_antecedent(), &[0]);
+        assert!((model.rules[0].lift - 1.5).abs() < 1e-6);
+        assert_eq!(model.itemsets[&2][&vec![0, 1]], 3);
+    }
+
+    #[test]
+    fn test_rejects_negative_rules() {
+        let mut model = model();
+        model.rules[0].negated_consequent = true;
+        let result = write_pmml(vec![], &model);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_rejects_unknown_item() {
+        let pmml =
+            to_pmml(&model()).replace(r#"<ItemRef itemRef="0"/>"#, r#"<ItemRef itemRef="9"/>"#);
+        assert!(matches!(read_pmml(&pmml), Err(Error::InvalidModel(_))));
+    }
+
+    #[test]
+    fn test_rejects_missing_model() {
+        assert!(matches!(read_pmml("<PMML/>"), Err(Error::Format(_))));
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Lines},
+    path::Path,
+};
+
+use crate::{
+    interner::Interner,
+    types::{ItemId, Transaction},
+};
+
+/// Layout of a transaction file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Format {
+    /// One transaction per line, with items separated by `delimiter`
+    Basket { delimiter: char },
+    /// SPMF: one transaction per line, with integer items separated by
+    /// spaces. Lines starting with `#`, `%` or `@` are comments or metadata,
+    /// except for `@ITEM=<id>=<name>`, which names an item.
+    Spmf,
+    /// One `transaction_id<delimiter>item` pair per line. Pairs of the same
+    /// transaction need not be adjacent.
+    Long { delimiter: char, has_header: bool },
+}
+
+/// Read all transactions of the file at `path`. Items are assigned IDs in
+/// order of first appearance.
+pub fn read_transactions<P: AsRef<Path>>(
+    path: P,
+    format: &Format,
+) -> io::Result<(Vec<Transaction>, Interner)> {
+    read_transactions_from(BufReader::new(fs::File::open(path)?), format)
+}
+
+/// Read all transactions from `reader`, e.g. standard input.
+pub fn read_transactions_from<R: BufRead>(
+    reader: R,
+    format: &Format,
+) -> io::Result<(Vec<Transaction>, Interner)> {
+    let mut interner = Interner::new();
+    let transactions = match *format {
+        Format::Basket { delimiter } => read_baskets(reader, delimiter, &mut interner)?,
+        Format::Spmf => read_spmf(reader, &mut interner)?,
+        Format::Long {
+            delimiter,
+            has_header,
+        } => read_long(reader, delimiter, has_header, &mut interner)?,
+    };
+    Ok((transactions, interner))
+}
+
+/// Read one transaction per line. Blank items are ignored, so a blank line is
+/// an empty transaction.
+pub fn read_baskets<R: BufRead>(
+    reader: R,
+    delimiter: char,
+    interner: &mut Interner,
+) -> io::Result<Vec<Transaction>> {
+    reader
+        .lines()
+        .map(|line| Ok(parse_basket(&line?, delimiter, interner)))
+        .collect()
+}
+
+fn parse_basket(line: &str, delimiter: char, interner: &mut Interner) -> Transaction {
+    let transaction = line
+        .split(delimiter)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| interner.intern(item))
+        .collect();
+    normalize(transaction)
+}
+
+/// Read transactions in SPMF format. Items are named by their `@ITEM` name if
+/// there is one, else by their integer. Annotations such as `#SUP: 3` after
+/// the items are ignored.
+pub fn read_spmf<R: BufRead>(reader: R, interner: &mut Interner) -> io::Result<Vec<Transaction>> {
+    let mut names: HashMap<u64, String> = HashMap::new();
+    let mut transactions = vec![];
+    for (line_no, line) in reader.lines().enumerate() {
+        if let Some(transaction) = parse_spmf(&line?, line_no, &mut names, interner)? {
+            transactions.push(transaction);
+        }
+    }
+    Ok(transactions)
+}
+
+/// Parse a line of SPMF, recording `@ITEM` names in `names`. Returns `None`
+/// for comments and metadata.
+fn parse_spmf(
+    line: &str,
+    line_no: usize,
+    names: &mut HashMap<u64, String>,
+    interner: &mut Interner,
+) -> io::Result<Option<Transaction>> {
+    let line = line.trim();
+    if let Some(item) = line.strip_prefix("@ITEM=") {
+        let (item, name) = item
+            .split_once('=')
+            .ok_or_else(|| invalid_data(line_no, "expected @ITEM=<id>=<name>"))?;
+        let item = parse_item(item, line_no)?;
+        names.insert(item, String::from(name));
+        return Ok(None);
+    }
+    if line.is_empty() || line.starts_with(['#', '%', '@']) {
+        return Ok(None);
+    }
+    // Itemsets written as SPMF are followed by e.g. `#SUP: 3`
+    let items = line.split('#').next().unwrap_or_default();
+    let transaction = items
+        .split_whitespace()
+        .map(|item| {
+            let item = parse_item(item, line_no)?;
+            Ok(match names.get(&item) {
+                Some(name) => interner.intern(name),
+                None => interner.intern(&item.to_string()),
+            })
+        })
+        .collect::<io::Result<Vec<ItemId>>>()?;
+    Ok(Some(normalize(transaction)))
+}
+
+/// Read `transaction_id<delimiter>item` pairs and group them by transaction
+/// ID. Transactions are returned in order of first appearance.
+pub fn read_long<R: BufRead>(
+    reader: R,
+    delimiter: char,
+    has_header: bool,
+    interner: &mut Interner,
+) -> io::Result<Vec<Transaction>> {
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    let mut transactions: Vec<Transaction> = vec![];
+    for (line_no, line) in reader.lines().enumerate().skip(has_header as usize) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (transaction_id, item) = line
+            .split_once(delimiter)
+            .ok_or_else(|| invalid_data(line_no, "expected transaction_id and item"))?;
+        let (transaction_id, item) = (transaction_id.trim(), item.trim());
+        let index = match indices.get(transaction_id) {
+            Some(&index) => index,
+            None => {
+                indices.insert(String::from(transaction_id), transactions.len());
+                transactions.push(vec![]);
+                transactions.len() - 1
+            }
+        };
+        if !item.is_empty() {
+            transactions[index].push(interner.intern(item));
+        }
+    }
+    Ok(transactions.into_iter().map(normalize).collect())
+}
+
+/// Read the file at `path` in chunks of at most `chunk_size` transactions, so
+/// that only one chunk is in memory at a time. Items are assigned IDs by
+/// `interner`, so reading the file again with the same interner gives the same
+/// IDs. The long format cannot be read in chunks, as its transactions may be
+/// spread over the whole file.
+pub fn read_chunks<'i, P: AsRef<Path>>(
+    path: P,
+    format: &Format,
+    chunk_size: usize,
+    interner: &'i mut Interner,
+) -> io::Result<Chunks<'i, BufReader<fs::File>>> {
+    read_chunks_from(
+        BufReader::new(fs::File::open(path)?),
+        format,
+        chunk_size,
+        interner,
+    )
+}
+
+/// Read transactions from `reader` in chunks; see `read_chunks`.
+pub fn read_chunks_from<'i, R: BufRead>(
+    reader: R,
+    format: &Format,
+    chunk_size: usize,
+    interner: &'i mut Interner,
+) -> io::Result<Chunks<'i, R>> {
+    if chunk_size == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "chunk size must be positive",
+        ));
+    }
+    if let Format::Long { .. } = format {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the long format cannot be read in chunks",
+        ));
+    }
+    Ok(Chunks {
+        lines: reader.lines().enumerate(),
+        format: format.clone(),
+        chunk_size,
+        names: HashMap::new(),
+        interner,
+    })
+}
+
+/// Iterator over chunks of transactions; see `read_chunks`.
+pub struct Chunks<'i, R> {
+    lines: std::iter::Enumerate<Lines<R>>,
+    format: Format,
+    chunk_size: usize,
+    names: HashMap<u64, String>,
+    interner: &'i mut Interner,
+}
+
+impl<'i, R: BufRead> Iterator for Chunks<'i, R> {
+    type Item = io::Result<Vec<Transaction>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut transactions = Vec::with_capacity(self.chunk_size);
+        while transactions.len() < self.chunk_size {
+            let (line_no, line) = match self.lines.next() {
+                Some((line_no, Ok(line))) => (line_no, line),
+                Some((_, Err(e))) => return Some(Err(e)),
+                None => break,
+            };
+            let transaction = match self.format {
+                Format::Basket { delimiter } => parse_basket(&line, delimiter, self.interner),
+                _ => match parse_spmf(&line, line_no, &mut self.names, self.interner) {
+                    Ok(Some(transaction)) => transaction,
+                    Ok(None) => continue,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            transactions.push(transaction);
+        }
+        if transactions.is_empty() {
+            None
+        } else {
+            Some(Ok(transactions))
+        }
+    }
+}
+
+fn normalize(mut transaction: Transaction) -> Transaction {
+    transaction.sort_unstable();
+    transaction.dedup();
+    transaction
+}
+
+fn parse_item(item: &str, line_no: usize) -> io::Result<u64> {
+    item.trim()
+        .parse()
+        .map_err(|_| invalid_data(line_no, &format!("invalid item {:?}", item)))
+}
+
+fn invalid_data(line_no: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_no + 1, message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn names(transactions: &[Transaction], interner: &Interner) -> Vec<Vec<String>> {
+        transactions
+            .iter()
+            .map(|transaction| {
+                let mut names: Vec<String> = transaction
+                    .iter()
+                    .map(|&item_id| String::from(interner.name(item_id).unwrap()))
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_baskets() {
+        let data = "bread, milk\nmilk,cheese,milk\n\nbread\n";
+        let mut interner = Interner::new();
+        let transactions = read_baskets(Cursor::new(data), ',', &mut interner).unwrap();
+
+        assert_eq!(transactions, vec![vec![0, 1], vec![1, 2], vec![], vec![0]]);
+        assert_eq!(names(&transactions, &interner)[1], vec!["cheese", "milk"]);
+    }
+
+    #[test]
+    fn test_read_spmf() {
+        let data = "@CONVERTED_FROM_TEXT\n@ITEM=1=bread\n@ITEM=2=milk\n# comment\n1 2\n2 3\n";
+        let mut interner = Interner::new();
+        let transactions = read_spmf(Cursor::new(data), &mut interner).unwrap();
+
+        assert_eq!(
+            names(&transactions, &interner),
+            vec![vec!["bread", "milk"], vec!["3", "milk"]]
+        );
+    }
+
+    #[test]
+    fn test_read_spmf_invalid_item() {
+        let mut interner = Interner::new();
+        let err = read_spmf(Cursor::new("1 2\n1 x\n"), &mut interner).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2"));
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let data = "@ITEM=1=bread\n1 2\n2 3\n# comment\n1\n";
+        let mut interner = Interner::new();
+        let (transactions, _) = read_transactions_from(Cursor::new(data), &Format::Spmf).unwrap();
+
+        let chunks: Vec<Vec<Transaction>> =
+            read_chunks_from(Cursor::new(data), &Format::Spmf, 2, &mut interner)
+                .unwrap()
+                .collect::<io::Result<_>>()
+                .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), transactions);
+
+        let long = Format::Long {
+            delimiter: ',',
+            has_header: false,
+        };
+        assert!(read_chunks_from(Cursor::new(data), &long, 2, &mut interner).is_err());
+        assert!(read_chunks_from(Cursor::new(data), &Format::Spmf, 0, &mut interner).is_err());
+    }
+
+    #[test]
+    fn test_read_long() {
+        let data = "transaction_id,item\nt2,milk\nt1,bread\nt2,cheese\nt1,milk\n";
+        let mut interner = Interner::new();
+        let transactions = read_long(Cursor::new(data), ',', true, &mut interner).unwrap();
+
+        assert_eq!(
+            names(&transactions, &interner),
+            vec![vec!["cheese", "milk"], vec!["bread", "milk"]]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use serde_json::json;
+
+use crate::{
+    rules::rule::Rule,
+    types::{FrequentItemsets, Inventory, ItemId, Itemset},
+};
+
+/// Output format of frequent itemsets and rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// SPMF: `1 2 #SUP: 3` per itemset and `1 ==> 2 #SUP: 3 #CONF: 0.75 #LIFT: 1.5`
+    /// per rule, with support counts. Items are numbered from 1 in order of
+    /// name, and named by `@ITEM=<number>=<name>` lines before the first row.
+    Spmf,
+    /// One row per itemset (`itemset,count,support`) or rule
+    /// (`antecedent,consequent,support,confidence,lift`), with the items of
+    /// an itemset separated by `;`. A `;` or `\` in an item name is escaped
+    /// with a `\`.
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Write frequent itemsets by size, then by item ID. The items of an itemset
+/// are written in order of name.
+pub fn write_itemsets<W: Write>(
+    mut writer: W,
+    itemsets: &FrequentItemsets,
+    inventory: &Inventory,
+    num_transactions: usize,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let mut itemset_counts: Vec<(&Itemset, u32)> = itemsets
+        .values()
+        .flatten()
+        .map(|(itemset, &count)| (itemset, count))
+        .collect();
+    itemset_counts.sort_unstable_by(|a, b| a.0.len().cmp(&b.0.len()).then(a.0.cmp(b.0)));
+
+    // SPMF numbers of the item names
+    let numbers = match format {
+        OutputFormat::Spmf => {
+            let items = itemset_counts
+                .iter()
+                .flat_map(|(itemset, _)| itemset.iter());
+            spmf_header(&mut writer, items, inventory)?
+        }
+        OutputFormat::Csv => {
+            writeln!(writer, "itemset,count,support")?;
+            BTreeMap::new()
+        }
+        OutputFormat::JsonLines => BTreeMap::new(),
+    };
+    for (itemset, count) in itemset_counts {
+        let names = names(itemset, inventory)?;
+        let support = count as f32 / num_transactions as f32;
+        match format {
+            OutputFormat::Spmf => {
+                writeln!(writer, "{} #SUP: {}", spmf_items(&names, &numbers), count)?
+            }
+            OutputFormat::Csv => writeln!(
+                writer,
+                "{},{},{}",
+                csv_field(&csv_list(&names)),
+                count,
+                support
+            )?,
+            OutputFormat::JsonLines => writeln!(
+                writer,
+                "{}",
+                json!({ "items": names, "count": count, "support": support })
+            )?,
+        }
+    }
+    writer.flush()
+}
+
+/// Write rules in the given order. A negated antecedent or consequent is
+/// prefixed with `¬`.
+pub fn write_rules<W: Write>(
+    mut writer: W,
+    rules: &[Rule],
+    inventory: &Inventory,
+    num_transactions: usize,
+    format: OutputFormat,
+) -> io::Result<()> {
+    // SPMF numbers of the item names
+    let numbers = match format {
+        OutputFormat::Spmf => {
+            let items = rules.iter().flat_map(|rule| rule.combi.iter());
+            spmf_header(&mut writer, items, inventory)?
+        }
+        OutputFormat::Csv => {
+            writeln!(writer, "antecedent,consequent,support,confidence,lift")?;
+            BTreeMap::new()
+        }
+        OutputFormat::JsonLines => BTreeMap::new(),
+    };
+    for rule in rules {
+        let antecedent = names(rule.get_antecedent(), inventory)?;
+        let consequent = names(rule.get_consequent(), inventory)?;
+        let negation = |negated: bool| if negated { "¬" } else { "" };
+        match format {
+            OutputFormat::Spmf => writeln!(
+                writer,
+                "{}{} ==> {}{} #SUP: {} #CONF: {} #LIFT: {}",
+                negation(rule.negated_antecedent),
+                spmf_items(&antecedent, &numbers),
+                negation(rule.negated_consequent),
+                spmf_items(&consequent, &numbers),
+                (rule.support * num_transactions as f32).round() as u32,
+                rule.confidence,
+                rule.lift
+            )?,
+            OutputFormat::Csv => writeln!(
+                writer,
+                "{},{},{},{},{}",
+                csv_field(&format!(
+                    "{}{}",
+                    negation(rule.negated_antecedent),
+                    csv_list(&antecedent)
+                )),
+                csv_field(&format!(
+                    "{}{}",
+                    negation(rule.negated_consequent),
+                    csv_list(&consequent)
+                )),
+                rule.support,
+                rule.confidence,
+                rule.lift
+            )?,
+            OutputFormat::JsonLines => writeln!(
+                writer,
+                "{}",
+                json!({
+                    "antecedent": antecedent,
+                    "consequent": consequent,
+                    "support": rule.support,
+                    "confidence": rule.confidence,
+                    "lift": rule.lift,
+                    "negated_antecedent": rule.negated_antecedent,
+                    "negated_consequent": rule.negated_consequent,
+                })
+            )?,
+        }
+    }
+    writer.flush()
+}
+
+fn names<'l>(items: &[ItemId], inventory: &Inventory<'l>) -> io::Result<Vec<&'l str>> {
+    let mut names = items
+        .iter()
+        .map(|item_id| {
+            inventory.get(item_id).copied().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown item {}", item_id),
+                )
+            })
+        })
+        .collect::<io::Result<Vec<&str>>>()?;
+    names.sort_unstable();
+    Ok(names)
+}
+
+/// Number the names of `items` from 1 in order, writing an `@ITEM` line for
+/// each
+fn spmf_header<'l, 'i, W: Write>(
+    writer: &mut W,
+    items: impl Iterator<Item = &'i ItemId>,
+    inventory: &Inventory<'l>,
+) -> io::Result<BTreeMap<&'l str, usize>> {
+    let mut item_names = BTreeSet::new();
+    for item_id in items {
+        let name = names(&[*item_id], inventory)?[0];
+        if name.contains(['\n', '\r']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("item {:?} cannot be written in SPMF", name),
+            ));
+        }
+        item_names.insert(name);
+    }
+    let numbers: BTreeMap<&str, usize> = item_names.into_iter().zip(1..).collect();
+    for (name, number) in &numbers {
+        writeln!(writer, "@ITEM={}={}", number, name)?;
+    }
+    Ok(numbers)
+}
+
+/// The SPMF numbers of `names`, which are in order of name
+fn spmf_items(names: &[&str], numbers: &BTreeMap<&str, usize>) -> String {
+    names
+        .iter()
+        .map(|name| numbers[name].to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Join item names with `;`, escaping `;` and `\` in them
+fn csv_list(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| name.replace('\\', "\\\\").replace(';', "\\;"))
+        .collect::<Vec<String>>()
+        .join(";")
+}
+
+/// Quote a CSV field if needed (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, io::Cursor};
+
+    use crate::{interner::Interner, io::read::read_spmf};
+
+    fn inventory() -> Inventory<'static> {
+        vec![
+            (0, "bread"),
+            (1, "milk"),
+            (2, "cheese, aged"),
+            (3, "salt;pepper"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn itemsets() -> FrequentItemsets {
+        let mut itemsets: FrequentItemsets = HashMap::new();
+        itemsets.insert(
+            1,
+            vec![(vec![0], 3), (vec![1], 4), (vec![2], 2)]
+                .into_iter()
+                .collect(),
+        );
+        itemsets.insert(2, vec![(vec![0, 1], 3)].into_iter().collect());
+        itemsets
+    }
+
+    fn rules() -> Vec<Rule> {
+        let mut rule = Rule::from_split(&[0], &[1], false, false);
+        rule.support = 0.75;
+        rule.confidence = 1.0;
+        rule.lift = 1.0;
+        let mut negative = Rule::from_split(&[2], &[0], false, true);
+        negative.support = 0.25;
+        negative.confidence = 0.5;
+        negative.lift = 2.0;
+        vec![rule, negative]
+    }
+
+    fn to_string(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut buffer = vec![];
+        write(&mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_itemsets_spmf() {
+        let output = to_string(|buffer| {
+            write_itemsets(buffer, &itemsets(), &inventory(), 4, OutputFormat::Spmf)
+        });
+        assert_eq!(
+            output,
+            "@ITEM=1=bread\n@ITEM=2=cheese, aged\n@ITEM=3=milk\n\
+             1 #SUP: 3\n3 #SUP: 4\n2 #SUP: 2\n1 3 #SUP: 3\n"
+        );
+
+        // Read back as transactions
+        let mut interner = Interner::new();
+        let transactions = read_spmf(Cursor::new(output), &mut interner).unwrap();
+        let names: Vec<Vec<&str>> = transactions
+            .iter()
+            .map(|transaction| {
+                let mut names: Vec<&str> = transaction
+                    .iter()
+                    .map(|&item_id| interner.name(item_id).unwrap())
+                    .collect();
+                names.sort_unstable();
+                names
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                vec!["bread"],
+                vec!["milk"],
+                vec!["cheese, aged"],
+                vec!["bread", "milk"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_itemsets_csv() {
+        let output = to_string(|buffer| {
+            write_itemsets(buffer, &itemsets(), &inventory(), 4, OutputFormat::Csv)
+        });
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "itemset,count,support");
+        assert_eq!(lines[3], "\"cheese, aged\",2,0.5");
+        assert_eq!(lines[4], "bread;milk,3,0.75");
+    }
+
+    #[test]
+    fn test_rules_spmf() {
+        let output =
+            to_string(|buffer| write_rules(buffer, &rules(), &inventory(), 4, OutputFormat::Spmf));
+        assert_eq!(
+            output,
+            "@ITEM=1=bread\n@ITEM=2=cheese, aged\n@ITEM=3=milk\n\
+             1 ==> 3 #SUP: 3 #CONF: 1 #LIFT: 1\n\
+             2 ==> ¬1 #SUP: 1 #CONF: 0.5 #LIFT: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_rules_csv() {
+        let output =
+            to_string(|buffer| write_rules(buffer, &rules(), &inventory(), 4, OutputFormat::Csv));
+        assert_eq!(
+            output,
+            "antecedent,consequent,support,confidence,lift\n\
+             bread,milk,0.75,1,1\n\
+             \"cheese, aged\",¬bread,0.25,0.5,2\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_escapes_item_lists() {
+        let mut itemsets = FrequentItemsets::new();
+        itemsets.insert(2, vec![(vec![0, 3], 1)].into_iter().collect());
+        let output = to_string(|buffer| {
+            write_itemsets(buffer, &itemsets, &inventory(), 4, OutputFormat::Csv)
+        });
+        assert_eq!(output.lines().nth(1), Some("bread;salt\\;pepper,1,0.25"));
+    }
+
+    #[test]
+    fn test_rules_json_lines() {
+        let output = to_string(|buffer| {
+            write_rules(buffer, &rules(), &inventory(), 4, OutputFormat::JsonLines)
+        });
+        let rows: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["antecedent"], json!(["bread"]));
+        assert_eq!(rows[0]["support"], json!(0.75));
+        assert_eq!(rows[1]["negated_consequent"], json!(true));
+    }
+
+    #[test]
+    fn test_unknown_item() {
+        let mut inventory = inventory();
+        inventory.remove(&1);
+        let result = write_rules(vec![], &rules(), &inventory, 4, OutputFormat::Csv);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
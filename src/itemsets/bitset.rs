@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use bitvec::prelude::*;
+use rayon::prelude::*;
+
+use crate::{
+    error::{Error, Result},
+    progress::MiningOptions,
+    types::{ItemCounts, ItemId, Itemset, ItemsetCounts, Transaction},
+};
+
+/// Bitsets pay off once the frequent items of an average transaction are at
+/// least this fraction of all frequent items: pairs then cost fewer word
+/// operations as ANDs of bitsets than as walks over the transactions.
+const MIN_DENSITY: f32 = 0.125;
+
+type Bitset = BitVec<Lsb0, u64>;
+
+/// For each item, the set of transactions containing it, one bit each
+pub struct ItemBitsets {
+    bitsets: HashMap<ItemId, Bitset>,
+}
+
+impl ItemBitsets {
+    /// Bytes taken by the bitsets of `num_items` items over `num_transactions`
+    pub fn memory(num_items: usize, num_transactions: usize) -> usize {
+        let num_words = (num_transactions + 63) / 64;
+        num_items.saturating_mul(num_words * std::mem::size_of::<u64>())
+    }
+
+    /// Whether the bitsets of `num_items` items over `num_transactions` fit
+    /// within `max_memory` bytes, if any
+    pub fn fit(num_items: usize, num_transactions: usize, max_memory: Option<usize>) -> bool {
+        max_memory.map_or(true, |max| Self::memory(num_items, num_transactions) <= max)
+    }
+
+    /// Bitsets of `items` over `transactions`
+    pub fn new<I: IntoIterator<Item = ItemId>>(items: I, transactions: &[Transaction]) -> Self {
+        let mut bitsets: HashMap<ItemId, Bitset> = items
+            .into_iter()
+            .map(|item| (item, bitvec![Lsb0, u64; 0; transactions.len()]))
+            .collect();
+        for (index, transaction) in transactions.iter().enumerate() {
+            for item in transaction {
+                if let Some(bitset) = bitsets.get_mut(item) {
+                    bitset.set(index, true);
+                }
+            }
+        }
+        Self { bitsets }
+    }
+
+    /// No. of transactions containing every item of the non-empty `itemset`,
+    /// whose items must all have bitsets. `scratch` holds the AND of all
+    /// items but the last.
+    fn support_count(&self, itemset: &[ItemId], scratch: &mut Vec<u64>) -> u32 {
+        let words = |item| self.bitsets[item].as_raw_slice();
+        let (last, init) = itemset.split_last().expect("empty itemset");
+        let prefix = match init {
+            [] => return self.bitsets[last].count_ones() as u32,
+            [first] => words(first),
+            [first, rest @ ..] => {
+                scratch.clear();
+                scratch.extend_from_slice(words(first));
+                for item in rest {
+                    scratch
+                        .iter_mut()
+                        .zip(words(item))
+                        .for_each(|(a, b)| *a &= b);
+                }
+                scratch
+            }
+        };
+        // Word-wise, which the compiler vectorizes where the target allows
+        prefix
+            .iter()
+            .zip(words(last))
+            .map(|(a, b)| (a & b).count_ones())
+            .sum()
+    }
+
+    /// Count the candidates by ANDing the bitsets of their items, keeping
+    /// those with at least `min_support_count`. Candidates must be non-empty
+    /// and made of items with bitsets.
+    pub fn count_all(
+        &self,
+        candidates: Vec<Itemset>,
+        min_support_count: usize,
+        options: &MiningOptions,
+    ) -> Result<ItemsetCounts> {
+        if candidates.iter().any(|candidate| {
+            candidate.is_empty()
+                || candidate
+                    .iter()
+                    .any(|item| !self.bitsets.contains_key(item))
+        }) {
+            return Err(Error::InvalidParameter(String::from(
+                "candidates must be non-empty and made of items with bitsets",
+            )));
+        }
+        candidates
+            .into_par_iter()
+            .map_init(Vec::new, |scratch, candidate| {
+                options.check()?;
+                let count = self.support_count(&candidate, scratch);
+                Ok((candidate, count))
+            })
+            .filter(|result| {
+                result
+                    .as_ref()
+                    .map_or(true, |&(_, count)| count as usize >= min_support_count)
+            })
+            .collect()
+    }
+}
+
+/// Whether the frequent items of `item_counts` are dense enough in
+/// `transactions` for bitsets
+pub fn is_dense(item_counts: &ItemCounts, transactions: &[Transaction]) -> bool {
+    if item_counts.is_empty() || transactions.is_empty() {
+        return false;
+    }
+    let num_frequent: usize = transactions
+        .iter()
+        .flatten()
+        .filter(|item| item_counts.contains_key(item))
+        .count();
+    let avg_basket = num_frequent as f32 / transactions.len() as f32;
+    avg_basket / item_counts.len() as f32 >= MIN_DENSITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    fn transactions() -> Vec<Transaction> {
+        // Over 64 transactions, to span words
+        (0..150)
+            .map(|i: usize| (0..5).filter(|item| i % (item + 2) != 0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_count_all() {
+        let transactions = transactions();
+        let item_counts = hashmap! { 0 => 75, 1 => 100, 3 => 120 };
+        let bitsets = ItemBitsets::new(item_counts.keys().copied(), &transactions);
+        let candidates = vec![vec![0], vec![0, 1], vec![0, 3], vec![0, 1, 3]];
+
+        let counts = bitsets
+            .count_all(candidates.clone(), 50, &MiningOptions::default())
+            .unwrap();
+
+        let expected: ItemsetCounts = candidates
+            .into_iter()
+            .map(|candidate| {
+                let count = transactions
+                    .iter()
+                    .filter(|transaction| candidate.iter().all(|item| transaction.contains(item)))
+                    .count();
+                (candidate, count as u32)
+            })
+            .filter(|&(_, count)| count >= 50)
+            .collect();
+        assert_eq!(counts, expected);
+        assert_eq!(counts.len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_candidates() {
+        let bitsets = ItemBitsets::new(vec![0, 1], &transactions());
+        for candidates in [vec![vec![0], vec![]], vec![vec![0, 2]]] {
+            assert!(matches!(
+                bitsets.count_all(candidates, 1, &MiningOptions::default()),
+                Err(Error::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_fit() {
+        // 3 words per item
+        assert_eq!(ItemBitsets::memory(2, 150), 48);
+        assert!(ItemBitsets::fit(2, 150, None));
+        assert!(ItemBitsets::fit(2, 150, Some(48)));
+        assert!(!ItemBitsets::fit(2, 150, Some(47)));
+    }
+
+    #[test]
+    fn test_is_dense() {
+        let item_counts = hashmap! { 0 => 2, 1 => 2 };
+        assert!(is_dense(&item_counts, &[vec![0, 1], vec![0, 1, 2]]));
+
+        let item_counts: ItemCounts = (0..100).map(|item| (item, 1)).collect();
+        let transactions: Vec<Transaction> = (0..100).map(|item| vec![item]).collect();
+        assert!(!is_dense(&item_counts, &transactions));
+        assert!(!is_dense(&item_counts, &[]));
+    }
+}
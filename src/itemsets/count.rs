@@ -1,157 +1,533 @@
 #![allow(non_snake_case)]
 
 use crate::{
-    itemsets::search::generate_candidates_from_prev,
+    error::{check_fraction, check_max_length, Result},
+    itemsets::{
+        bitset::{is_dense, ItemBitsets},
+        pairs::{count_pairs, PairLayout},
+        search::{
+            generate_candidates_excluding, generate_candidates_from_prev, num_candidates_from_prev,
+        },
+        trie::CandidateTrie,
+    },
+    progress::{BudgetExceeded, MiningOptions},
     types::{
-        FrequentItemsets, Inventory, ItemCounts, ItemId, Itemset, ItemsetCounts, ItemsetLength,
-        RawTransaction, RawTransactionId, ReverseLookup, Transaction,
+        FrequentItemsets, Inventory, ItemCounts, ItemGroups, ItemId, Itemset, ItemsetCounts,
+        ItemsetLength, RawTransaction, RawTransactionId, ReverseLookup, Transaction,
     },
 };
-use itertools::{Combinations, Itertools};
+use itertools::Itertools;
 use pyo3::prelude::pyfunction;
 use rayon::prelude::*;
-use std::collections::{hash_map::Keys, HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
 
 const APPROX_NUM_UNIQUE_ITEMS: usize = 1024; // arbitrary
 const APPROX_NUM_ITEMS_IN_1_TRANSACTION: usize = 16; // arbitrary
 
+/// How candidates are counted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Counting {
+    /// `Bitset` if the frequent items are dense in the transactions, otherwise
+    /// `Trie`
+    #[default]
+    Auto,
+    /// Check every candidate against every transaction
+    Scan,
+    /// Walk every transaction once through a prefix trie of the candidates
+    Trie,
+    /// AND the bitsets of the transactions containing each item of a candidate,
+    /// or `Trie` if the bitsets would exceed the memory budget
+    Bitset,
+}
+
+/// Order in which item IDs are reassigned after the first pass, so that they
+/// do not depend on hashing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemOrder {
+    /// By first appearance in the transactions, then by name
+    FirstSeen,
+    /// By descending support, then by name
+    Frequency,
+    /// By name
+    Lexical,
+}
+
+/// Order in which frequent items are relabeled before level 2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SupportOrder {
+    /// Most frequent item first
+    Descending,
+    /// Least frequent item first
+    Ascending,
+}
+
 /// Generate frequent itemsets from a list of transactions.
 pub fn generate_frequent_itemsets_id(
     raw_transactions: Vec<RawTransactionId>,
     min_support: f32,
     k: ItemsetLength,
-) -> FrequentItemsets {
-    let mut all_frequent_itemsets: FrequentItemsets = HashMap::with_capacity(k);
+) -> Result<FrequentItemsets> {
+    check_max_length(k)?;
+    let start = Instant::now();
     let N = raw_transactions.len() as f32;
     let min_support_count = (min_support * N).ceil() as usize;
 
     // 1-itemset
-    let (item_counts, mut transactions) =
-        generate_frequent_1_itemset_counts_id(raw_transactions, min_support);
+    let (item_counts, transactions) =
+        generate_frequent_1_itemset_counts_id(raw_transactions, min_support)?;
+    let num_items = transactions.iter().flatten().collect::<HashSet<_>>().len();
+
+    generate_frequent_itemsets_from_counts(
+        item_counts,
+        num_items,
+        transactions,
+        min_support_count,
+        k,
+        &MiningOptions::default(),
+        start,
+        None,
+    )
+    .map(|(frequent_itemsets, _)| frequent_itemsets)
+}
 
-    // 2-itemset
-    if k == 1 {
-        let frequent_1_itemset_counts: ItemsetCounts = convert_to_itemset_counts(item_counts);
-        all_frequent_itemsets.insert(1, frequent_1_itemset_counts);
-    } else {
-        transactions.retain(|transaction| transaction.len() >= 2);
-        let candidates = item_counts.keys().combinations(2);
-        let frequent_2_itemset_counts: ItemsetCounts =
-            generate_frequent_2_itemset_counts(
-                candidates,
-                &transactions,
-                min_support_count,
-            );
-        let frequent_1_itemset_counts: ItemsetCounts = convert_to_itemset_counts(item_counts);
+/// Generate frequent itemsets from a list of transactions.
+pub fn generate_frequent_itemsets(
+    raw_transactions: Vec<RawTransaction>,
+    min_support: f32,
+    k: ItemsetLength,
+) -> Result<(FrequentItemsets, Inventory)> {
+    let (frequent_itemsets, inventory, _) = generate_frequent_itemsets_with_options(
+        raw_transactions,
+        min_support,
+        k,
+        &MiningOptions::default(),
+    )?;
+    Ok((frequent_itemsets, inventory))
+}
 
-        all_frequent_itemsets.insert(1, frequent_1_itemset_counts);
-        all_frequent_itemsets.insert(2, frequent_2_itemset_counts);
-    }
+/// Generate frequent itemsets from a list of transactions, reporting progress
+/// after each level and stopping early if cancelled. If a level exceeds the
+/// budget, the smaller itemsets are returned along with `BudgetExceeded`.
+pub fn generate_frequent_itemsets_with_options<'l>(
+    raw_transactions: Vec<RawTransaction<'l>>,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+) -> Result<(FrequentItemsets, Inventory<'l>, Option<BudgetExceeded>)> {
+    mine_raw_transactions(raw_transactions, min_support, k, options, None)
+}
 
-    // k-itemset, k >= 3
-    for size in 3..=k {
-        transactions.retain(|transaction| transaction.len() >= size);
-        let candidates = generate_candidates_from_prev(&all_frequent_itemsets[&(size - 1_usize)]);
-        let frequent_itemset_counts = generate_frequent_k_itemset_counts(
-            candidates,
-            &transactions,
-            min_support_count,
-        );
+/// Like `generate_frequent_itemsets`, also returning the counts of the
+/// candidates of each size found infrequent, e.g. for negative rules over
+/// items that rarely occur together
+pub fn generate_frequent_and_infrequent_itemsets(
+    raw_transactions: Vec<RawTransaction>,
+    min_support: f32,
+    k: ItemsetLength,
+) -> Result<(FrequentItemsets, FrequentItemsets, Inventory)> {
+    let mut infrequent_itemsets = HashMap::with_capacity(k);
+    let (frequent_itemsets, inventory, _) = mine_raw_transactions(
+        raw_transactions,
+        min_support,
+        k,
+        &MiningOptions::default(),
+        Some(&mut infrequent_itemsets),
+    )?;
+    Ok((frequent_itemsets, infrequent_itemsets, inventory))
+}
 
-        all_frequent_itemsets.insert(size, frequent_itemset_counts);
+fn mine_raw_transactions<'l>(
+    raw_transactions: Vec<RawTransaction<'l>>,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+    infrequent_itemsets: Option<&mut FrequentItemsets>,
+) -> Result<(FrequentItemsets, Inventory<'l>, Option<BudgetExceeded>)> {
+    check_max_length(k)?;
+    let start = Instant::now();
+    let N = raw_transactions.len() as f32;
+    let min_support_count = (min_support * N).ceil() as usize;
+
+    // 1-itemset
+    let (mut item_counts, mut inventory, mut transactions) =
+        generate_frequent_1_itemset_counts(raw_transactions, min_support)?;
+    if let Some(order) = options.item_order {
+        relabel(&mut item_counts, &mut inventory, &mut transactions, order);
     }
 
-    all_frequent_itemsets
+    let (frequent_itemsets, exceeded) = generate_frequent_itemsets_from_counts(
+        item_counts,
+        inventory.len(),
+        transactions,
+        min_support_count,
+        k,
+        options,
+        start,
+        infrequent_itemsets,
+    )?;
+    Ok((frequent_itemsets, inventory, exceeded))
 }
 
-/// Generate frequent itemsets from a list of transactions.
-pub fn generate_frequent_itemsets(
-    raw_transactions: Vec<RawTransaction>,
+/// Generate frequent itemsets from a list of transactions that are already
+/// sorted lists of item IDs, e.g. read from a file.
+pub fn generate_frequent_itemsets_from_transactions(
+    transactions: Vec<Transaction>,
     min_support: f32,
     k: ItemsetLength,
-) -> (FrequentItemsets, Inventory) {
-    let mut all_frequent_itemsets: FrequentItemsets = HashMap::with_capacity(k);
-    let N = raw_transactions.len() as f32;
+) -> Result<FrequentItemsets> {
+    generate_frequent_itemsets_from_transactions_with_options(
+        transactions,
+        min_support,
+        k,
+        &MiningOptions::default(),
+    )
+    .map(|(frequent_itemsets, _)| frequent_itemsets)
+}
+
+/// Like `generate_frequent_itemsets_from_transactions`, reporting progress
+/// after each level and stopping early if cancelled or over budget.
+pub fn generate_frequent_itemsets_from_transactions_with_options(
+    transactions: Vec<Transaction>,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+) -> Result<(FrequentItemsets, Option<BudgetExceeded>)> {
+    mine_transactions(transactions, min_support, k, options, None)
+}
+
+/// Like `generate_frequent_itemsets_from_transactions_with_options`, also
+/// returning the counts of the candidates of each size found infrequent
+pub fn generate_frequent_and_infrequent_itemsets_from_transactions_with_options(
+    transactions: Vec<Transaction>,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+) -> Result<(FrequentItemsets, FrequentItemsets, Option<BudgetExceeded>)> {
+    let mut infrequent_itemsets = HashMap::with_capacity(k);
+    let (frequent_itemsets, exceeded) = mine_transactions(
+        transactions,
+        min_support,
+        k,
+        options,
+        Some(&mut infrequent_itemsets),
+    )?;
+    Ok((frequent_itemsets, infrequent_itemsets, exceeded))
+}
+
+fn mine_transactions(
+    transactions: Vec<Transaction>,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+    infrequent_itemsets: Option<&mut FrequentItemsets>,
+) -> Result<(FrequentItemsets, Option<BudgetExceeded>)> {
+    check_fraction("min_support", min_support)?;
+    check_max_length(k)?;
+    let start = Instant::now();
+    let N = transactions.len() as f32;
     let min_support_count = (min_support * N).ceil() as usize;
 
     // 1-itemset
-    let (item_counts, inventory, mut transactions) =
-        generate_frequent_1_itemset_counts(raw_transactions, min_support);
+    let mut item_counts: ItemCounts = HashMap::with_capacity(APPROX_NUM_UNIQUE_ITEMS);
+    for &item in transactions.iter().flatten() {
+        *item_counts.entry(item).or_insert(0) += 1;
+    }
+    let num_items = item_counts.len();
+    item_counts.retain(|_, &mut support_count| support_count as usize >= min_support_count);
+
+    generate_frequent_itemsets_from_counts(
+        item_counts,
+        num_items,
+        transactions,
+        min_support_count,
+        k,
+        options,
+        start,
+        infrequent_itemsets,
+    )
+}
+
+/// Generate frequent k-itemsets, k >= 2, given the frequent 1-itemsets out of
+/// `num_items` distinct items. Stops before a level whose candidates exceed the
+/// budget of `options`. With `options.relabel`, levels 2 and up are mined with
+/// the frequent items relabeled by support, and returned with their own IDs.
+/// With `infrequent_itemsets`, every candidate counted is kept, those below
+/// `min_support_count` in `infrequent_itemsets`.
+#[allow(clippy::too_many_arguments)]
+fn generate_frequent_itemsets_from_counts(
+    item_counts: ItemCounts,
+    num_items: usize,
+    mut transactions: Vec<Transaction>,
+    min_support_count: usize,
+    k: ItemsetLength,
+    options: &MiningOptions,
+    start: Instant,
+    mut infrequent_itemsets: Option<&mut FrequentItemsets>,
+) -> Result<(FrequentItemsets, Option<BudgetExceeded>)> {
+    // Candidates are counted down to 0 to keep the infrequent ones
+    let count_threshold = if infrequent_itemsets.is_some() {
+        0
+    } else {
+        min_support_count
+    };
+    let mut all_frequent_itemsets: FrequentItemsets = HashMap::with_capacity(k);
+    options.check()?;
+    options.report(start, 1, num_items, item_counts.len());
 
     // 2-itemset
     if k == 1 {
         let frequent_1_itemset_counts: ItemsetCounts = convert_to_itemset_counts(item_counts);
         all_frequent_itemsets.insert(1, frequent_1_itemset_counts);
-    } else {
-        transactions.retain(|transaction| transaction.len() >= 2);
-        let candidates = item_counts.keys().combinations(2);
-        let frequent_2_itemset_counts: ItemsetCounts =
-            generate_frequent_2_itemset_counts(
-                candidates,
-                &transactions,
-                min_support_count,
-            );
-        let frequent_1_itemset_counts: ItemsetCounts = convert_to_itemset_counts(item_counts);
-
-        all_frequent_itemsets.insert(1, frequent_1_itemset_counts);
-        all_frequent_itemsets.insert(2, frequent_2_itemset_counts);
+        return Ok((all_frequent_itemsets, None));
     }
+    let num_candidates = item_counts.len() * item_counts.len().saturating_sub(1) / 2;
+    if let Some(exceeded) = options.budget.check(2, num_candidates) {
+        all_frequent_itemsets.insert(1, convert_to_itemset_counts(item_counts));
+        return Ok((all_frequent_itemsets, Some(exceeded)));
+    }
+    let relabeled = options
+        .relabel
+        .map(|order| relabel_by_support(&item_counts, &mut transactions, order));
+    let frequent_items = relabeled
+        .as_ref()
+        .map_or(&item_counts, |(item_counts, _)| item_counts);
+    let groups: Option<ItemGroups> = options.item_groups.map(|groups| match &relabeled {
+        Some((_, original_ids)) => original_ids
+            .iter()
+            .enumerate()
+            .filter_map(|(item, original_id)| groups.get(original_id).map(|&g| (item, g)))
+            .collect(),
+        None => groups.clone(),
+    });
+    let same_group = |pair: &[ItemId]| match &groups {
+        Some(groups) => {
+            let group = |item| groups.get(item);
+            group(&pair[0]).is_some() && group(&pair[0]) == group(&pair[1])
+        }
+        None => false,
+    };
+    transactions.retain(|transaction| transaction.len() >= 2);
+    let bitsets = (match options.counting {
+        Counting::Auto => is_dense(frequent_items, &transactions),
+        counting => counting == Counting::Bitset,
+    } && ItemBitsets::fit(
+        frequent_items.len(),
+        transactions.len(),
+        options.budget.max_memory,
+    ))
+    .then(|| ItemBitsets::new(frequent_items.keys().copied(), &transactions));
+    let frequent_2_itemset_counts: ItemsetCounts = match &bitsets {
+        Some(bitsets) => {
+            let items: Itemset = frequent_items.keys().copied().sorted_unstable().collect();
+            let candidates = items
+                .into_iter()
+                .combinations(2)
+                .filter(|pair| !same_group(pair))
+                .collect();
+            bitsets.count_all(candidates, count_threshold, options)?
+        }
+        // Pairs are counted without candidates, so those of a group are
+        // dropped before they are joined. The triangular arrays are kept
+        // within the memory budget.
+        None => {
+            let layout = PairLayout::for_items(frequent_items.len(), options.budget.max_memory);
+            let mut counts = count_pairs(
+                frequent_items,
+                &transactions,
+                count_threshold,
+                layout,
+                options,
+            )?;
+            // The sparse layout only has the pairs that occur
+            if infrequent_itemsets.is_some() && layout == PairLayout::Sparse {
+                let items: Itemset = frequent_items.keys().copied().sorted_unstable().collect();
+                for pair in items.into_iter().combinations(2) {
+                    counts.entry(pair).or_insert(0);
+                }
+            }
+            counts.retain(|pair, _| !same_group(pair));
+            counts
+        }
+    };
+    let frequent_2_itemset_counts = split_infrequent(
+        frequent_2_itemset_counts,
+        2,
+        min_support_count,
+        infrequent_itemsets.as_deref_mut(),
+    );
+    options.report(start, 2, num_candidates, frequent_2_itemset_counts.len());
+    let frequent_1_itemset_counts: ItemsetCounts = convert_to_itemset_counts(item_counts);
+
+    all_frequent_itemsets.insert(1, frequent_1_itemset_counts);
+    all_frequent_itemsets.insert(2, frequent_2_itemset_counts);
 
     // k-itemset, k >= 3
+    let mut exceeded = None;
     for size in 3..=k {
+        let prev_frequent_itemsets = &all_frequent_itemsets[&(size - 1_usize)];
+        let num_candidates = num_candidates_from_prev(prev_frequent_itemsets);
+        exceeded = options.budget.check(size, num_candidates);
+        if exceeded.is_some() {
+            break;
+        }
         transactions.retain(|transaction| transaction.len() >= size);
-        let candidates = generate_candidates_from_prev(&all_frequent_itemsets[&(size - 1_usize)]);
-        let frequent_itemset_counts = generate_frequent_k_itemset_counts(
-            candidates,
-            &transactions,
+        let candidates = match &groups {
+            Some(groups) => generate_candidates_excluding(prev_frequent_itemsets, groups)?,
+            None => generate_candidates_from_prev(prev_frequent_itemsets)?,
+        };
+        let frequent_itemset_counts = match &bitsets {
+            Some(bitsets) => bitsets.count_all(candidates, count_threshold, options)?,
+            None => generate_frequent_k_itemset_counts(
+                candidates,
+                &transactions,
+                count_threshold,
+                options,
+            )?,
+        };
+        let frequent_itemset_counts = split_infrequent(
+            frequent_itemset_counts,
+            size,
             min_support_count,
+            infrequent_itemsets.as_deref_mut(),
         );
+        options.report(start, size, num_candidates, frequent_itemset_counts.len());
 
         all_frequent_itemsets.insert(size, frequent_itemset_counts);
     }
 
-    (all_frequent_itemsets, inventory)
+    if let Some((_, original_ids)) = relabeled {
+        for (_, itemset_counts) in all_frequent_itemsets
+            .iter_mut()
+            .filter(|&(&size, _)| size >= 2)
+            .chain(
+                infrequent_itemsets
+                    .into_iter()
+                    .flat_map(|itemsets| itemsets.iter_mut()),
+            )
+        {
+            *itemset_counts = itemset_counts
+                .drain()
+                .map(|(itemset, count)| {
+                    let mut itemset: Itemset =
+                        itemset.iter().map(|&item| original_ids[item]).collect();
+                    itemset.sort_unstable();
+                    (itemset, count)
+                })
+                .collect();
+        }
+    }
+
+    Ok((all_frequent_itemsets, exceeded))
 }
 
-fn generate_frequent_2_itemset_counts(
-    candidates: Combinations<Keys<usize, u32>>,
-    transactions: &[Transaction],
+/// Keep the itemsets of `counts` with at least `min_support_count`, moving the
+/// others of this `size` into `infrequent_itemsets` if given
+fn split_infrequent(
+    counts: ItemsetCounts,
+    size: ItemsetLength,
     min_support_count: usize,
+    infrequent_itemsets: Option<&mut FrequentItemsets>,
 ) -> ItemsetCounts {
-    candidates
-        .par_bridge()
-        .into_par_iter()
-        .filter_map(|candidate| {
-            let candidate_count = transactions
-                .par_iter()
-                .filter(|transaction| candidate.iter().all(|item| transaction.contains(item)))
-                .count();
-            if candidate_count >= min_support_count {
-                let mut freq: Itemset = candidate.iter().map(|x| **x).collect();
-                freq.sort_unstable();
-                Some((freq, candidate_count as u32))
-            } else {
-                None
-            }
-        })
-        .collect()
+    match infrequent_itemsets {
+        Some(infrequent_itemsets) => {
+            let (frequent, infrequent): (ItemsetCounts, ItemsetCounts) = counts
+                .into_iter()
+                .partition(|&(_, count)| count as usize >= min_support_count);
+            infrequent_itemsets.insert(size, infrequent);
+            frequent
+        }
+        None => counts,
+    }
 }
 
-/// includes pruning
-fn generate_frequent_k_itemset_counts(
+/// Relabel the frequent items of `item_counts` 0, 1, ... in `order` of
+/// support, and drop the infrequent items from `transactions`. Returns the
+/// counts by new ID, and the original ID of each new ID.
+fn relabel_by_support(
+    item_counts: &ItemCounts,
+    transactions: &mut [Transaction],
+    order: SupportOrder,
+) -> (ItemCounts, Vec<ItemId>) {
+    let mut items: Vec<(ItemId, u32)> = item_counts
+        .iter()
+        .map(|(&item, &count)| (item, count))
+        .collect();
+    match order {
+        SupportOrder::Descending => {
+            items.sort_unstable_by_key(|&(item, count)| (std::cmp::Reverse(count), item))
+        }
+        SupportOrder::Ascending => items.sort_unstable_by_key(|&(item, count)| (count, item)),
+    }
+
+    let mut new_ids = vec![None; items.iter().map(|&(item, _)| item + 1).max().unwrap_or(0)];
+    for (new_id, &(item, _)) in items.iter().enumerate() {
+        new_ids[item] = Some(new_id);
+    }
+    transactions.par_iter_mut().for_each(|transaction| {
+        *transaction = transaction
+            .iter()
+            .filter_map(|&item| new_ids.get(item).copied().flatten())
+            .collect();
+        transaction.sort_unstable();
+    });
+
+    let relabeled_counts = items
+        .iter()
+        .enumerate()
+        .map(|(new_id, &(_, count))| (new_id, count))
+        .collect();
+    let original_ids = items.into_iter().map(|(item, _)| item).collect();
+    (relabeled_counts, original_ids)
+}
+
+/// includes pruning; checks for cancellation before counting each candidate,
+/// or each chunk of transactions with `Counting::Trie` (and `Counting::Auto`).
+/// With `Counting::Bitset`, falls back to the trie if the bitsets of the items
+/// of the candidates would exceed the memory budget.
+/// Transactions need not be sorted.
+pub fn generate_frequent_k_itemset_counts(
     candidate_counts: Vec<Itemset>,
     transactions: &[Transaction],
     min_support_count: usize,
-) -> ItemsetCounts {
+    options: &MiningOptions,
+) -> Result<ItemsetCounts> {
+    let items: HashSet<ItemId> = match options.counting {
+        Counting::Bitset => candidate_counts.iter().flatten().copied().collect(),
+        _ => HashSet::new(),
+    };
+    let bitsets_fit = ItemBitsets::fit(items.len(), transactions.len(), options.budget.max_memory);
+    match options.counting {
+        Counting::Bitset if bitsets_fit => {
+            return ItemBitsets::new(items, transactions).count_all(
+                candidate_counts,
+                min_support_count,
+                options,
+            );
+        }
+        Counting::Auto | Counting::Trie | Counting::Bitset => {
+            return CandidateTrie::new(candidate_counts)?.count_all(
+                transactions,
+                min_support_count,
+                options,
+            )
+        }
+        Counting::Scan => {}
+    }
     candidate_counts
         .par_iter()
         .filter_map(|candidate| {
+            if let Err(e) = options.check() {
+                return Some(Err(e));
+            }
             let candidate_count = transactions
                 .par_iter()
                 .filter(|transaction| candidate.iter().all(|item| transaction.contains(item)))
                 .count();
             if candidate_count >= min_support_count {
-                Some((candidate.iter().copied().collect(), candidate_count as u32))
+                Some(Ok((candidate.to_vec(), candidate_count as u32)))
             } else {
                 None
             }
@@ -159,6 +535,59 @@ fn generate_frequent_k_itemset_counts(
         .collect()
 }
 
+/// Reassign the item IDs of the first pass in `order`
+fn relabel(
+    item_counts: &mut ItemCounts,
+    inventory: &mut Inventory,
+    transactions: &mut [Transaction],
+    order: ItemOrder,
+) {
+    let name = |item: &ItemId| inventory[item];
+    let mut items: Vec<ItemId> = match order {
+        ItemOrder::FirstSeen => {
+            let mut seen = HashSet::with_capacity(inventory.len());
+            let mut items = Vec::with_capacity(inventory.len());
+            for transaction in transactions.iter() {
+                let start = items.len();
+                items.extend(transaction.iter().filter(|&&item| seen.insert(item)));
+                items[start..].sort_unstable_by_key(name);
+            }
+            items
+        }
+        ItemOrder::Frequency | ItemOrder::Lexical => inventory.keys().copied().collect(),
+    };
+    match order {
+        ItemOrder::FirstSeen => {}
+        ItemOrder::Frequency => {
+            let mut counts: ItemCounts = HashMap::with_capacity(inventory.len());
+            for &item in transactions.iter().flatten() {
+                *counts.entry(item).or_insert(0) += 1;
+            }
+            items.sort_unstable_by_key(|item| (std::cmp::Reverse(counts[item]), name(item)));
+        }
+        ItemOrder::Lexical => items.sort_unstable_by_key(name),
+    }
+
+    let mut new_ids = vec![0; items.len()];
+    for (new_id, &item) in items.iter().enumerate() {
+        new_ids[item] = new_id;
+    }
+    *item_counts = item_counts
+        .drain()
+        .map(|(item, count)| (new_ids[item], count))
+        .collect();
+    *inventory = inventory
+        .drain()
+        .map(|(item, name)| (new_ids[item], name))
+        .collect();
+    for transaction in transactions {
+        for item in transaction.iter_mut() {
+            *item = new_ids[*item];
+        }
+        transaction.sort_unstable();
+    }
+}
+
 fn convert_to_itemset_counts(item_counts: ItemCounts) -> ItemsetCounts {
     item_counts.into_iter().map(|(k, v)| (vec![k], v)).collect()
 }
@@ -169,7 +598,8 @@ fn convert_to_itemset_counts(item_counts: ItemCounts) -> ItemsetCounts {
 pub fn generate_frequent_1_itemset_counts_id(
     raw_transactions: Vec<HashSet<ItemId>>,
     min_support: f32,
-) -> (ItemCounts, Vec<Transaction>) {
+) -> Result<(ItemCounts, Vec<Transaction>)> {
+    check_fraction("min_support", min_support)?;
     let N = raw_transactions.len() as f32;
 
     let mut item_counts = HashMap::with_capacity(APPROX_NUM_UNIQUE_ITEMS);
@@ -193,7 +623,7 @@ pub fn generate_frequent_1_itemset_counts_id(
     // Prune
     item_counts.retain(|_, &mut support_count| support_count >= min_support_count);
 
-    (item_counts, transactions_new)
+    Ok((item_counts, transactions_new))
 }
 
 /// 1-itemset
@@ -202,7 +632,8 @@ pub fn generate_frequent_1_itemset_counts_id(
 pub fn generate_frequent_1_itemset_counts(
     raw_transactions: Vec<HashSet<&str>>,
     min_support: f32,
-) -> (ItemCounts, Inventory, Vec<Transaction>) {
+) -> Result<(ItemCounts, Inventory<'_>, Vec<Transaction>)> {
+    check_fraction("min_support", min_support)?;
     let N = raw_transactions.len() as f32;
 
     let mut reverse_lookup: ReverseLookup = HashMap::with_capacity(APPROX_NUM_UNIQUE_ITEMS);
@@ -245,12 +676,16 @@ pub fn generate_frequent_1_itemset_counts(
     // Prune
     item_counts.retain(|_, &mut support_count| support_count >= min_support_count);
 
-    (item_counts, inventory, transactions_new)
+    Ok((item_counts, inventory, transactions_new))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        error::Error,
+        progress::{Budget, CancellationToken, Progress},
+    };
     use maplit::hashmap;
 
     const A: &str = "Item A";
@@ -294,8 +729,13 @@ mod tests {
         let transactions = vec![vec![0, 1]];
         let candidate_counts = vec![vec![0], vec![1]];
 
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 0);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            0,
+            &MiningOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(frequent_itemsets, hashmap! { vec![0] => 1, vec![1] => 1 });
     }
@@ -305,8 +745,13 @@ mod tests {
         let transactions = vec![vec![10, 11], vec![10, 12]];
         let candidate_counts = vec![vec![10], vec![11]];
 
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 2);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            2,
+            &MiningOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(frequent_itemsets, hashmap! {vec![10] => 2})
     }
@@ -323,8 +768,13 @@ mod tests {
         ];
         let candidate_counts = vec![vec![10], vec![11], vec![12], vec![15]];
 
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 3);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            3,
+            &MiningOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             frequent_itemsets,
@@ -352,8 +802,13 @@ mod tests {
             vec![11, 13],
             vec![11, 15],
         ];
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 3);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            3,
+            &MiningOptions::default(),
+        )
+        .unwrap();
         assert_eq!(frequent_itemsets, hashmap! { vec![10, 13] => 3});
     }
 
@@ -362,8 +817,13 @@ mod tests {
         let transactions = vec![vec![10, 11], vec![10, 13]];
         let candidate_counts = vec![vec![10], vec![11]];
 
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 2);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            2,
+            &MiningOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(frequent_itemsets, hashmap! { vec![10] => 2 });
     }
@@ -373,8 +833,13 @@ mod tests {
         let transactions = vec![vec![10, 11, 13]];
         let candidate_counts = vec![vec![10], vec![11]];
 
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 0);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            0,
+            &MiningOptions::default(),
+        )
+        .unwrap();
         assert_eq!(
             frequent_itemsets,
             hashmap! { vec![10] => 1,
@@ -387,8 +852,13 @@ mod tests {
         let transactions = vec![vec![10, 11, 13], vec![10]];
         let candidate_counts = vec![vec![10], vec![11]];
 
-        let frequent_itemsets =
-            generate_frequent_k_itemset_counts(candidate_counts, &transactions, 0);
+        let frequent_itemsets = generate_frequent_k_itemset_counts(
+            candidate_counts,
+            &transactions,
+            0,
+            &MiningOptions::default(),
+        )
+        .unwrap();
         assert_eq!(
             frequent_itemsets,
             hashmap! { vec![10] => 2,
@@ -400,7 +870,7 @@ mod tests {
     fn create_counts_one_itemset_with_sorted_transaction_ids() {
         let raw_transactions = vec![raw_transaction![A, B, D], raw_transaction![A]];
         let (itemset_counts, inventory, transaction_ids) =
-            generate_frequent_1_itemset_counts(raw_transactions, 0.0);
+            generate_frequent_1_itemset_counts(raw_transactions, 0.0).unwrap();
         let lookup = get_reverse_lookup(inventory);
 
         assert_eq!(itemset_counts.len(), 3);
@@ -420,7 +890,8 @@ mod tests {
     #[test]
     fn create_counts_one_itemset_with_min_support_1() {
         let raw_transactions = vec![raw_transaction![A, B, D], raw_transaction![A]];
-        let (itemset_counts, inventory, _) = generate_frequent_1_itemset_counts(raw_transactions, 1.0);
+        let (itemset_counts, inventory, _) =
+            generate_frequent_1_itemset_counts(raw_transactions, 1.0).unwrap();
         let lookup = get_reverse_lookup(inventory);
 
         assert_eq!(itemset_counts.len(), 1);
@@ -435,7 +906,8 @@ mod tests {
             raw_transaction![B],
             raw_transaction![A, C],
         ];
-        let (itemset_counts, inventory, _) = generate_frequent_1_itemset_counts(raw_transactions, 0.5);
+        let (itemset_counts, inventory, _) =
+            generate_frequent_1_itemset_counts(raw_transactions, 0.5).unwrap();
         let lookup = get_reverse_lookup(inventory);
 
         assert_eq!(itemset_counts.len(), 3);
@@ -469,7 +941,7 @@ mod tests {
             vec![13] => 0,
             vec![14] => 0,
         };
-        let candidate_counts = generate_candidates_from_prev(&itemset_counts);
+        let candidate_counts = generate_candidates_from_prev(&itemset_counts).unwrap();
 
         let expected = vec![vec![10, 13], vec![10, 14], vec![13, 14]];
 
@@ -484,7 +956,8 @@ mod tests {
             hashset![A, B, C],
             hashset![B, D],
         ];
-        let (frequent_itemsets, inventory) = generate_frequent_itemsets(transactions, 0.01, 3);
+        let (frequent_itemsets, inventory) =
+            generate_frequent_itemsets(transactions, 0.01, 3).unwrap();
         let lookup = get_reverse_lookup(inventory);
 
         let expected = hashmap! {
@@ -516,7 +989,8 @@ mod tests {
             hashset![A, B, C],
             hashset![B, D],
         ];
-        let (frequent_itemsets, inventory) = generate_frequent_itemsets(transactions, 0.5, 3);
+        let (frequent_itemsets, inventory) =
+            generate_frequent_itemsets(transactions, 0.5, 3).unwrap();
         let lookup = get_reverse_lookup(inventory);
 
         let expected = hashmap! {
@@ -543,7 +1017,8 @@ mod tests {
             hashset![A, B, C],
             hashset![B, C],
         ];
-        let (frequent_itemsets, inventory) = generate_frequent_itemsets(transactions, 0.5, 5);
+        let (frequent_itemsets, inventory) =
+            generate_frequent_itemsets(transactions, 0.5, 5).unwrap();
         let lookup = get_reverse_lookup(inventory);
 
         let expected = hashmap! {
@@ -565,6 +1040,302 @@ mod tests {
         assert_eq!(frequent_itemsets, expected);
     }
 
+    #[test]
+    fn test_progress_per_level() {
+        let transactions = vec![
+            hashset![A, B],
+            hashset![A, C],
+            hashset![A, B, C],
+            hashset![B, D],
+        ];
+        let levels = std::sync::Mutex::new(vec![]);
+        let progress = |progress: &Progress| {
+            levels.lock().unwrap().push((
+                progress.level,
+                progress.num_candidates,
+                progress.num_frequent,
+            ))
+        };
+        let options = MiningOptions::default().with_progress(&progress);
+        generate_frequent_itemsets_with_options(transactions, 0.5, 2, &options).unwrap();
+
+        // 4 items, 3 frequent; 3 pairs, 2 frequent
+        assert_eq!(levels.into_inner().unwrap(), vec![(1, 4, 3), (2, 3, 2)]);
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let transactions = vec![hashset![A, B], hashset![A, B, C]];
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = MiningOptions::default().with_cancel(token.clone());
+
+        let result = generate_frequent_itemsets_with_options(transactions, 0.5, 3, &options);
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        let candidate_counts = vec![vec![0], vec![1]];
+        let result =
+            generate_frequent_k_itemset_counts(candidate_counts, &[vec![0, 1]], 0, &options);
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_budget() {
+        let transactions = || {
+            vec![
+                hashset![A, B],
+                hashset![A, C],
+                hashset![A, B, C],
+                hashset![B, C],
+            ]
+        };
+        let mine = |budget: Budget| {
+            let options = MiningOptions::default().with_budget(budget);
+            let (frequent_itemsets, _, exceeded) =
+                generate_frequent_itemsets_with_options(transactions(), 0.5, 3, &options).unwrap();
+            (frequent_itemsets, exceeded)
+        };
+
+        // 3 pairs, then 1 triple
+        let (frequent_itemsets, exceeded) = mine(Budget {
+            max_candidates: Some(3),
+            max_memory: None,
+        });
+        assert_eq!(exceeded, None);
+        assert_eq!(frequent_itemsets[&2].len(), 3);
+        assert!(frequent_itemsets[&3].is_empty());
+
+        let (frequent_itemsets, exceeded) = mine(Budget {
+            max_candidates: Some(2),
+            max_memory: None,
+        });
+        assert_eq!(
+            exceeded,
+            Some(BudgetExceeded {
+                level: 2,
+                num_candidates: 3,
+                memory: Budget::memory(2, 3),
+            })
+        );
+        assert_eq!(frequent_itemsets.len(), 1);
+        assert_eq!(frequent_itemsets[&1].len(), 3);
+
+        let (frequent_itemsets, exceeded) = mine(Budget {
+            max_candidates: None,
+            max_memory: Some(Budget::memory(2, 3) - 1),
+        });
+        assert_eq!(exceeded.unwrap().level, 2);
+        assert_eq!(frequent_itemsets.len(), 1);
+    }
+
+    #[test]
+    fn test_counting() {
+        let transactions: Vec<Transaction> = (0..100)
+            .map(|i: usize| (0..8).filter(|item| (i >> item) & 1 == 0).collect())
+            .collect();
+        let mine = |counting: Counting| {
+            let options = MiningOptions::default().with_counting(counting);
+            generate_frequent_itemsets_from_transactions_with_options(
+                transactions.clone(),
+                0.1,
+                5,
+                &options,
+            )
+            .unwrap()
+        };
+        let (frequent_itemsets, _) = mine(Counting::Scan);
+        assert!(!frequent_itemsets[&4].is_empty());
+        for &counting in &[Counting::Auto, Counting::Trie, Counting::Bitset] {
+            assert_eq!(mine(counting).0, frequent_itemsets);
+        }
+    }
+
+    #[test]
+    fn test_infrequent_itemsets() {
+        // 0 and 1 never occur together
+        let transactions: Vec<Transaction> = (0..20)
+            .map(|i: usize| match i % 4 {
+                0 => vec![0, 2],
+                1 => vec![1, 2],
+                2 => vec![0, 2, 3],
+                _ => vec![1, 3],
+            })
+            .collect();
+        let mine = |options: MiningOptions| {
+            generate_frequent_and_infrequent_itemsets_from_transactions_with_options(
+                transactions.clone(),
+                0.3,
+                3,
+                &options,
+            )
+            .unwrap()
+        };
+        let expected =
+            generate_frequent_itemsets_from_transactions(transactions.clone(), 0.3, 3).unwrap();
+
+        let (frequent_itemsets, infrequent_itemsets, _) = mine(MiningOptions::default());
+        assert_eq!(frequent_itemsets, expected);
+        assert_eq!(infrequent_itemsets[&2][&vec![0, 1]], 0);
+        assert_eq!(infrequent_itemsets[&2][&vec![1, 2]], 5);
+        // Every pair of the 4 frequent items is counted
+        assert_eq!(expected[&2].len() + infrequent_itemsets[&2].len(), 6);
+        assert!(infrequent_itemsets[&2]
+            .keys()
+            .all(|pair| !expected[&2].contains_key(pair)));
+
+        for &counting in &[Counting::Scan, Counting::Trie, Counting::Bitset] {
+            for relabel in [None, Some(SupportOrder::Ascending)] {
+                let mut options = MiningOptions::default().with_counting(counting);
+                options.relabel = relabel;
+                let (frequent, infrequent, _) = mine(options);
+                assert_eq!(frequent, frequent_itemsets);
+                assert_eq!(infrequent, infrequent_itemsets);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitset_memory_budget() {
+        // Bitsets of 3 items over 10000 transactions take 3768 bytes, more
+        // than the candidates
+        let transactions: Vec<Transaction> = (0..10000)
+            .map(|i: usize| (0..3).filter(|item| (i >> item) & 1 == 0).collect())
+            .collect();
+        let budget = Budget {
+            max_candidates: None,
+            max_memory: Some(1000),
+        };
+        assert!(!ItemBitsets::fit(3, transactions.len(), budget.max_memory));
+        let mine = |counting: Counting| {
+            let options = MiningOptions::default()
+                .with_counting(counting)
+                .with_budget(budget);
+            generate_frequent_itemsets_from_transactions_with_options(
+                transactions.clone(),
+                0.1,
+                3,
+                &options,
+            )
+            .unwrap()
+        };
+
+        let (frequent_itemsets, exceeded) = mine(Counting::Bitset);
+        assert_eq!(exceeded, None);
+        assert_eq!(frequent_itemsets[&3], hashmap! { vec![0, 1, 2] => 1250 });
+        assert_eq!((frequent_itemsets, exceeded), mine(Counting::Scan));
+
+        let candidates = vec![vec![0, 1], vec![1, 2]];
+        let options = MiningOptions::default()
+            .with_counting(Counting::Bitset)
+            .with_budget(budget);
+        assert_eq!(
+            generate_frequent_k_itemset_counts(candidates, &transactions, 0, &options).unwrap(),
+            hashmap! { vec![0, 1] => 2500, vec![1, 2] => 2500 }
+        );
+    }
+
+    #[test]
+    fn test_item_order() {
+        let transactions = || {
+            vec![
+                hashset![D, C],
+                hashset![A, B, C],
+                hashset![B, C],
+                hashset![B, D],
+            ]
+        };
+        let mine = |order: ItemOrder| {
+            let options = MiningOptions::default().with_item_order(order);
+            let (frequent_itemsets, inventory, _) =
+                generate_frequent_itemsets_with_options(transactions(), 0.5, 2, &options).unwrap();
+            (frequent_itemsets, inventory)
+        };
+
+        for &(order, names) in &[
+            (ItemOrder::FirstSeen, [C, D, A, B]),
+            (ItemOrder::Frequency, [B, C, D, A]),
+            (ItemOrder::Lexical, [A, B, C, D]),
+        ] {
+            let (frequent_itemsets, inventory) = mine(order);
+            let expected: Inventory = names.iter().copied().enumerate().collect();
+            assert_eq!(inventory, expected);
+            let reverse_lookup = get_reverse_lookup(inventory);
+            assert_eq!(
+                frequent_itemsets[&2],
+                hashmap! { sorted_vec![reverse_lookup[B], reverse_lookup[C]] => 2 }
+            );
+            assert_eq!(mine(order).0, frequent_itemsets);
+        }
+    }
+
+    #[test]
+    fn test_relabel() {
+        let transactions: Vec<Transaction> = (0..100)
+            .map(|i: usize| {
+                (0..10)
+                    .filter(|item| (i * 7 + item) % (item + 2) < 2)
+                    .collect()
+            })
+            .collect();
+        let mine = |options: MiningOptions| {
+            generate_frequent_itemsets_from_transactions_with_options(
+                transactions.clone(),
+                0.1,
+                4,
+                &options,
+            )
+            .unwrap()
+        };
+        let (expected, _) = mine(MiningOptions::default());
+        assert!(!expected[&3].is_empty());
+
+        for &order in &[SupportOrder::Descending, SupportOrder::Ascending] {
+            for &counting in &[Counting::Trie, Counting::Bitset] {
+                let options = MiningOptions::default()
+                    .with_relabel(order)
+                    .with_counting(counting);
+                assert_eq!(mine(options), (expected.clone(), None));
+            }
+        }
+
+        // Levels below the one over budget are complete
+        for &max_candidates in &[10, 20, 30, 40] {
+            let options = MiningOptions::default()
+                .with_relabel(SupportOrder::Descending)
+                .with_budget(Budget {
+                    max_candidates: Some(max_candidates),
+                    max_memory: None,
+                });
+            let (frequent_itemsets, exceeded) = mine(options);
+            let max_level = exceeded.map_or(4, |exceeded| exceeded.level - 1);
+            assert_eq!(frequent_itemsets.len(), max_level);
+            for size in 1..=max_level {
+                assert_eq!(frequent_itemsets[&size], expected[&size]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let transactions = || vec![hashset![A, B]];
+        assert!(matches!(
+            generate_frequent_itemsets(transactions(), 1.5, 3),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            generate_frequent_itemsets(transactions(), -0.1, 3),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            generate_frequent_itemsets(transactions(), f32::NAN, 3),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            generate_frequent_itemsets(transactions(), 0.5, 0),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+
     fn get_reverse_lookup(inventory: Inventory) -> ReverseLookup {
         inventory.into_iter().map(|(k, v)| (v, k)).collect()
     }
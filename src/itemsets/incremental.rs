@@ -0,0 +1,185 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    error::{check_fraction, check_max_length, Error, Result},
+    itemsets::{count::generate_frequent_k_itemset_counts, search::generate_candidates_from_prev},
+    progress::MiningOptions,
+    types::{FrequentItemsets, Itemset, ItemsetCounts, ItemsetLength, Transaction},
+};
+
+/// Update previously mined frequent itemsets after transactions are added to
+/// and/or deleted from the database (FUP2, Cheung et al.).
+///
+/// `transactions` is the database the previous itemsets were mined from, and
+/// must include the `deleted` transactions. `previous` must have been mined
+/// with the same `min_support`. The result is the same as mining the updated
+/// database from scratch, but the previous database is only rescanned for
+/// candidates that were not frequent before and may have become frequent.
+pub fn update_frequent_itemsets(
+    previous: &FrequentItemsets,
+    transactions: &[Transaction],
+    added: &[Transaction],
+    deleted: &[Transaction],
+    min_support: f32,
+    k: ItemsetLength,
+) -> Result<FrequentItemsets> {
+    check_fraction("min_support", min_support)?;
+    check_max_length(k)?;
+    if deleted.len() > transactions.len() {
+        return Err(Error::InvalidParameter(String::from(
+            "cannot delete more transactions than there are",
+        )));
+    }
+    let previous_N = transactions.len();
+    let N = previous_N + added.len() - deleted.len();
+    let previous_min_support_count = (min_support * previous_N as f32).ceil() as usize;
+    let min_support_count = (min_support * N as f32).ceil() as usize;
+
+    let options = MiningOptions::default();
+    let mut all_frequent_itemsets: FrequentItemsets = FrequentItemsets::with_capacity(k);
+
+    for size in 1..=k {
+        let candidates: Vec<Itemset> = if size == 1 {
+            transactions
+                .iter()
+                .chain(added)
+                .flatten()
+                .copied()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|item| vec![item])
+                .collect()
+        } else {
+            generate_candidates_from_prev(&all_frequent_itemsets[&(size - 1)])?
+        };
+
+        let added_counts =
+            generate_frequent_k_itemset_counts(candidates.clone(), added, 0, &options)?;
+        let deleted_counts =
+            generate_frequent_k_itemset_counts(candidates.clone(), deleted, 0, &options)?;
+        let delta =
+            |itemset: &Itemset| added_counts[itemset] as i64 - deleted_counts[itemset] as i64;
+
+        let previous_counts = previous.get(&size);
+        let mut frequent_itemset_counts = ItemsetCounts::with_capacity(candidates.len());
+        let mut to_rescan = vec![];
+
+        for candidate in candidates {
+            match previous_counts {
+                Some(previous_counts) => match previous_counts.get(&candidate) {
+                    Some(&count) => {
+                        let count = count as i64 + delta(&candidate);
+                        if count >= min_support_count as i64 {
+                            frequent_itemset_counts.insert(candidate, count as u32);
+                        }
+                    }
+                    None => {
+                        // Not frequent before, so its previous count is below
+                        // the previous threshold, if any
+                        let max_count =
+                            (previous_min_support_count as i64 - 1).max(0) + delta(&candidate);
+                        if max_count >= min_support_count as i64 {
+                            to_rescan.push(candidate);
+                        }
+                    }
+                },
+                // Not mined before
+                None => to_rescan.push(candidate),
+            }
+        }
+
+        let rescanned_counts =
+            generate_frequent_k_itemset_counts(to_rescan, transactions, 0, &options)?;
+        for (candidate, count) in rescanned_counts {
+            let count = count as i64 + delta(&candidate);
+            if count >= min_support_count as i64 {
+                frequent_itemset_counts.insert(candidate, count as u32);
+            }
+        }
+
+        all_frequent_itemsets.insert(size, frequent_itemset_counts);
+    }
+
+    Ok(all_frequent_itemsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::itemsets::count::generate_frequent_itemsets_id;
+
+    fn transactions(seed: usize, n: usize) -> Vec<Transaction> {
+        (0..n)
+            .map(|i| {
+                (0..8)
+                    .filter(|j| (i * 7 + j * 3 + seed) % 5 < 2 + j % 2)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn mine(transactions: &[Transaction], min_support: f32, k: usize) -> FrequentItemsets {
+        generate_frequent_itemsets_id(
+            transactions
+                .iter()
+                .map(|transaction| transaction.iter().copied().collect())
+                .collect(),
+            min_support,
+            k,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_insertions() {
+        let old = transactions(0, 40);
+        let added = transactions(3, 25);
+        let previous = mine(&old, 0.3, 4);
+
+        let updated = update_frequent_itemsets(&previous, &old, &added, &[], 0.3, 4).unwrap();
+
+        let all: Vec<Transaction> = old.iter().chain(added.iter()).cloned().collect();
+        assert_eq!(updated, mine(&all, 0.3, 4));
+    }
+
+    #[test]
+    fn test_insertions_and_deletions() {
+        let old = transactions(1, 50);
+        let deleted: Vec<Transaction> = old[..20].to_vec();
+        let added = transactions(4, 10);
+        let previous = mine(&old, 0.25, 3);
+
+        let updated = update_frequent_itemsets(&previous, &old, &added, &deleted, 0.25, 3).unwrap();
+
+        let all: Vec<Transaction> = old[20..].iter().chain(added.iter()).cloned().collect();
+        assert_eq!(updated, mine(&all, 0.25, 3));
+    }
+
+    #[test]
+    fn test_longer_than_previous() {
+        let old = transactions(2, 30);
+        let added = transactions(0, 5);
+        let previous = mine(&old, 0.2, 2);
+
+        let updated = update_frequent_itemsets(&previous, &old, &added, &[], 0.2, 4).unwrap();
+
+        let all: Vec<Transaction> = old.iter().chain(added.iter()).cloned().collect();
+        assert_eq!(updated, mine(&all, 0.2, 4));
+    }
+
+    #[test]
+    fn test_empty_history() {
+        let added = transactions(0, 20);
+        let previous = mine(&[], 0.3, 3);
+
+        let updated = update_frequent_itemsets(&previous, &[], &added, &[], 0.3, 3).unwrap();
+        assert_eq!(updated, mine(&added, 0.3, 3));
+
+        // A single item that exactly meets the threshold
+        let added = vec![vec![0], vec![], vec![]];
+        let previous = mine(&[], 0.1, 2);
+        let updated = update_frequent_itemsets(&previous, &[], &added, &[], 0.1, 2).unwrap();
+        assert_eq!(updated, mine(&added, 0.1, 2));
+        assert_eq!(updated[&1][&vec![0]], 1);
+    }
+}
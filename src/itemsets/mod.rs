@@ -1,2 +1,10 @@
+pub mod bitset;
 pub mod count;
-mod search;
+pub mod incremental;
+pub mod pairs;
+pub mod partition;
+pub mod sample;
+pub mod search;
+pub mod stream;
+pub mod trie;
+pub mod window;
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, mem};
+
+use rayon::prelude::*;
+
+use crate::{
+    error::Result,
+    progress::MiningOptions,
+    types::{ItemCounts, ItemId, ItemsetCounts, Transaction},
+};
+
+/// Transactions counted between checks for cancellation
+const CHUNK_SIZE: usize = 1024;
+
+/// Most bytes taken by the triangular arrays of counts of all threads. Above,
+/// only the pairs that occur are counted, in a hashmap.
+const MAX_TRIANGULAR_MEMORY: usize = 1 << 28;
+
+/// How the counts of pairs of frequent items are stored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PairLayout {
+    /// A count for every pair, indexed by the ranks of its items
+    Triangular,
+    /// A count for every pair that occurs in some transaction
+    Sparse,
+}
+
+impl PairLayout {
+    /// The layout for pairs of `num_items` frequent items, sparse if the
+    /// triangular arrays would take more than `max_memory` bytes
+    pub fn for_items(num_items: usize, max_memory: Option<usize>) -> Self {
+        let max_memory =
+            max_memory.map_or(MAX_TRIANGULAR_MEMORY, |max| max.min(MAX_TRIANGULAR_MEMORY));
+        if triangular_memory(num_items) <= max_memory {
+            PairLayout::Triangular
+        } else {
+            PairLayout::Sparse
+        }
+    }
+}
+
+fn num_pairs(num_items: usize) -> usize {
+    num_items * num_items.saturating_sub(1) / 2
+}
+
+/// Bytes taken by the triangular arrays of counts of all threads
+fn triangular_memory(num_items: usize) -> usize {
+    num_pairs(num_items)
+        .saturating_mul(mem::size_of::<u32>())
+        .saturating_mul(rayon::current_num_threads().max(1))
+}
+
+/// Split `transactions` into one part per thread, each counted into its own
+/// accumulator by `increment` on the ranks `i < j` of every pair of frequent
+/// items, then `merge` the accumulators. `None` if there are no transactions.
+fn count_per_thread<T, R, I, M>(
+    transactions: &[Transaction],
+    rank_of: &R,
+    options: &MiningOptions,
+    init: impl Fn() -> T + Sync,
+    increment: I,
+    merge: M,
+) -> Result<Option<T>>
+where
+    T: Send,
+    R: Fn(&ItemId) -> Option<usize> + Sync,
+    I: Fn(&mut T, usize, usize) + Sync,
+    M: Fn(T, T) -> T + Sync,
+{
+    let num_threads = rayon::current_num_threads().max(1);
+    let part_len = ((transactions.len() + num_threads - 1) / num_threads).max(1);
+    transactions
+        .par_chunks(part_len)
+        .map(|part| {
+            let mut counts = init();
+            let mut frequent = Vec::new();
+            for chunk in part.chunks(CHUNK_SIZE) {
+                options.check()?;
+                for transaction in chunk {
+                    frequent.clear();
+                    frequent.extend(transaction.iter().filter_map(rank_of));
+                    // Transactions need not be sorted or free of repeated
+                    // items, and pairs are (i, j), i < j
+                    frequent.sort_unstable();
+                    frequent.dedup();
+                    for (a, &i) in frequent.iter().enumerate() {
+                        for &j in &frequent[a + 1..] {
+                            increment(&mut counts, i, j);
+                        }
+                    }
+                }
+            }
+            Ok(counts)
+        })
+        .try_reduce_with(|a, b| Ok(merge(a, b)))
+        .transpose()
+}
+
+/// Count the pairs of the frequent items of `item_counts` over all
+/// transactions, keeping those with at least `min_support_count`. Each
+/// transaction, sorted or not and with repeated items or not, is walked once,
+/// incrementing the counts of its pairs.
+pub fn count_pairs(
+    item_counts: &ItemCounts,
+    transactions: &[Transaction],
+    min_support_count: usize,
+    layout: PairLayout,
+    options: &MiningOptions,
+) -> Result<ItemsetCounts> {
+    // Ranks follow item ids, so pairs of ranks are pairs of sorted items
+    let mut items: Vec<ItemId> = item_counts.keys().copied().collect();
+    items.sort_unstable();
+    let n = items.len();
+    let mut ranks = vec![usize::MAX; items.last().map_or(0, |&item| item + 1)];
+    for (rank, &item) in items.iter().enumerate() {
+        ranks[item] = rank;
+    }
+    let rank_of = |item: &ItemId| ranks.get(*item).copied().filter(|&rank| rank != usize::MAX);
+
+    let counts: Vec<((usize, usize), u32)> = match layout {
+        PairLayout::Triangular => {
+            let counts = count_per_thread(
+                transactions,
+                &rank_of,
+                options,
+                || vec![0; num_pairs(n)],
+                |counts, i, j| {
+                    // Pairs (i, j), j > i, follow those of smaller ranks
+                    counts[i * (2 * n - i - 1) / 2 + j - i - 1] += 1
+                },
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                    a
+                },
+            )?
+            .unwrap_or_default();
+            (0..n)
+                .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                .zip(counts)
+                .filter(|&(_, count)| count as usize >= min_support_count)
+                .collect()
+        }
+        PairLayout::Sparse => count_per_thread(
+            transactions,
+            &rank_of,
+            options,
+            HashMap::new,
+            |counts, i, j| *counts.entry((i, j)).or_insert(0) += 1,
+            |mut a, b| {
+                for (pair, count) in b {
+                    *a.entry(pair).or_insert(0) += count;
+                }
+                a
+            },
+        )?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|&(_, count)| count as usize >= min_support_count)
+        .collect(),
+    };
+
+    Ok(counts
+        .into_iter()
+        .map(|((i, j), count)| (vec![items[i], items[j]], count))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_count_pairs() {
+        // Item 5 is infrequent
+        let item_counts = hashmap! { 0 => 3, 2 => 3, 3 => 2, 7 => 2 };
+        let transactions = vec![vec![0, 2, 3], vec![0, 2, 5, 7], vec![2, 3, 7], vec![0, 5]];
+        let expected = hashmap! {
+            vec![0, 2] => 2,
+            vec![2, 3] => 2,
+            vec![2, 7] => 2,
+        };
+
+        for &layout in &[PairLayout::Triangular, PairLayout::Sparse] {
+            let counts = count_pairs(
+                &item_counts,
+                &transactions,
+                2,
+                layout,
+                &MiningOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(counts, expected);
+        }
+    }
+
+    #[test]
+    fn test_count_pairs_unsorted() {
+        let item_counts = hashmap! { 0 => 3, 2 => 3, 3 => 2, 7 => 2 };
+        let transactions = vec![vec![3, 2, 0], vec![7, 5, 2, 0], vec![7, 3, 2], vec![5, 0]];
+        let expected = hashmap! {
+            vec![0, 2] => 2,
+            vec![2, 3] => 2,
+            vec![2, 7] => 2,
+        };
+
+        for &layout in &[PairLayout::Triangular, PairLayout::Sparse] {
+            let counts = count_pairs(
+                &item_counts,
+                &transactions,
+                2,
+                layout,
+                &MiningOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(counts, expected);
+        }
+    }
+
+    #[test]
+    fn test_count_pairs_repeated_items() {
+        let item_counts = hashmap! { 0 => 2, 2 => 3, 3 => 2 };
+        let transactions = vec![vec![2, 0, 2], vec![3, 3, 2, 0, 0], vec![2, 3]];
+        let expected = hashmap! {
+            vec![0, 2] => 2,
+            vec![2, 3] => 2,
+        };
+
+        for &layout in &[PairLayout::Triangular, PairLayout::Sparse] {
+            let counts = count_pairs(
+                &item_counts,
+                &transactions,
+                2,
+                layout,
+                &MiningOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(counts, expected);
+        }
+    }
+
+    #[test]
+    fn test_layout_for_items() {
+        assert_eq!(PairLayout::for_items(0, None), PairLayout::Triangular);
+        assert_eq!(PairLayout::for_items(1000, None), PairLayout::Triangular);
+        assert_eq!(PairLayout::for_items(100_000, None), PairLayout::Sparse);
+        // 499,500 pairs take at least 2 MB
+        assert_eq!(
+            PairLayout::for_items(1000, Some(1 << 20)),
+            PairLayout::Sparse
+        );
+    }
+}
@@ -0,0 +1,224 @@
+#![allow(non_snake_case)]
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Instant,
+};
+
+use crate::{
+    error::{check_fraction, check_max_length, Result},
+    interner::Interner,
+    io::read::{read_chunks, Format},
+    itemsets::count::{
+        generate_frequent_itemsets_from_transactions_with_options,
+        generate_frequent_k_itemset_counts,
+    },
+    progress::{BudgetExceeded, MiningOptions},
+    types::{FrequentItemsets, Itemset, ItemsetCounts, ItemsetLength, Transaction},
+};
+
+/// Visits every chunk of the transactions in order, passing each to the
+/// callback. Called once per pass, so it must yield the same chunks each time.
+pub type ForEachChunk<'a> =
+    dyn FnMut(&mut dyn FnMut(Vec<Transaction>) -> Result<()>) -> Result<()> + 'a;
+
+/// Generate frequent itemsets of transactions too large for memory, by the
+/// partition algorithm of Savasere, Omiecinski and Navathe (SON).
+///
+/// The first pass mines each chunk for its locally frequent itemsets; an
+/// itemset frequent overall is frequent in at least one chunk. The second pass
+/// counts the union of these candidates over all chunks. Only one chunk and
+/// the candidates are in memory at a time, and the result is the same as of
+/// `generate_frequent_itemsets_from_transactions`.
+///
+/// If a chunk exceeds the budget of `options`, itemsets of that size and
+/// larger are missing from the result, as in `generate_frequent_itemsets_with_options`.
+pub fn generate_frequent_itemsets_partitioned(
+    for_each_chunk: &mut ForEachChunk,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+) -> Result<(FrequentItemsets, Option<BudgetExceeded>)> {
+    check_fraction("min_support", min_support)?;
+    check_max_length(k)?;
+    let start = Instant::now();
+    // Chunks report nothing; levels are only complete after the second pass
+    let chunk_options = MiningOptions {
+        progress: None,
+        ..options.clone()
+    };
+
+    // Pass 1: locally frequent itemsets
+    let mut candidates: HashMap<ItemsetLength, HashSet<Itemset>> = HashMap::new();
+    let mut exceeded: Option<BudgetExceeded> = None;
+    let mut N = 0;
+    for_each_chunk(&mut |chunk| {
+        N += chunk.len();
+        let (frequent_itemsets, chunk_exceeded) =
+            generate_frequent_itemsets_from_transactions_with_options(
+                chunk,
+                min_support,
+                k,
+                &chunk_options,
+            )?;
+        if let Some(chunk_exceeded) = chunk_exceeded {
+            if exceeded
+                .as_ref()
+                .map_or(true, |e| chunk_exceeded.level < e.level)
+            {
+                exceeded = Some(chunk_exceeded);
+            }
+        }
+        for (size, itemset_counts) in frequent_itemsets {
+            candidates
+                .entry(size)
+                .or_default()
+                .extend(itemset_counts.into_keys());
+        }
+        Ok(())
+    })?;
+    let max_level = exceeded.as_ref().map_or(k, |e| e.level - 1);
+    let candidates: Vec<Vec<Itemset>> = (1..=max_level)
+        .map(|size| {
+            candidates
+                .remove(&size)
+                .map_or_else(Vec::new, |level| level.into_iter().collect())
+        })
+        .collect();
+
+    // Pass 2: global counts of the candidates
+    let mut counts: Vec<ItemsetCounts> = vec![HashMap::new(); max_level];
+    for_each_chunk(&mut |chunk| {
+        for (level, itemsets) in counts.iter_mut().zip(&candidates) {
+            for (itemset, count) in
+                generate_frequent_k_itemset_counts(itemsets.clone(), &chunk, 0, options)?
+            {
+                *level.entry(itemset).or_insert(0) += count;
+            }
+        }
+        Ok(())
+    })?;
+
+    let min_support_count = (min_support * N as f32).ceil() as u32;
+    let mut all_frequent_itemsets: FrequentItemsets = HashMap::with_capacity(max_level);
+    for (size, level) in (1..=max_level).zip(counts) {
+        let num_candidates = candidates[size - 1].len();
+        let frequent_itemset_counts: ItemsetCounts = level
+            .into_iter()
+            .filter(|&(_, count)| count >= min_support_count)
+            .collect();
+        options.report(start, size, num_candidates, frequent_itemset_counts.len());
+        all_frequent_itemsets.insert(size, frequent_itemset_counts);
+    }
+
+    Ok((all_frequent_itemsets, exceeded))
+}
+
+/// Generate frequent itemsets of the file at `path`, reading it twice in
+/// chunks of `chunk_size` transactions. Returns the no. of transactions as well.
+pub fn generate_frequent_itemsets_from_file<P: AsRef<Path>>(
+    path: P,
+    format: &Format,
+    chunk_size: usize,
+    interner: &mut Interner,
+    min_support: f32,
+    k: ItemsetLength,
+    options: &MiningOptions,
+) -> Result<(FrequentItemsets, usize, Option<BudgetExceeded>)> {
+    let mut N = 0;
+    let (frequent_itemsets, exceeded) = generate_frequent_itemsets_partitioned(
+        &mut |visit| {
+            N = 0;
+            for chunk in read_chunks(&path, format, chunk_size, interner)? {
+                let chunk = chunk?;
+                N += chunk.len();
+                visit(chunk)?;
+            }
+            Ok(())
+        },
+        min_support,
+        k,
+        options,
+    )?;
+    Ok((frequent_itemsets, N, exceeded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::Error, itemsets::count::generate_frequent_itemsets_from_transactions,
+        progress::Budget,
+    };
+
+    fn transactions() -> Vec<Transaction> {
+        vec![
+            vec![0, 1, 2],
+            vec![0, 1],
+            vec![1, 2, 3],
+            vec![0, 2, 3],
+            vec![0, 1, 2, 3],
+            vec![3],
+            vec![1, 3],
+            vec![0, 1, 3],
+        ]
+    }
+
+    fn mine(
+        chunk_size: usize,
+        min_support: f32,
+        k: ItemsetLength,
+        options: &MiningOptions,
+    ) -> Result<(FrequentItemsets, Option<BudgetExceeded>)> {
+        let transactions = transactions();
+        generate_frequent_itemsets_partitioned(
+            &mut |visit| {
+                for chunk in transactions.chunks(chunk_size) {
+                    visit(chunk.to_vec())?;
+                }
+                Ok(())
+            },
+            min_support,
+            k,
+            options,
+        )
+    }
+
+    #[test]
+    fn test_same_as_apriori() {
+        for &min_support in &[0.1, 0.25, 0.5] {
+            let expected =
+                generate_frequent_itemsets_from_transactions(transactions(), min_support, 4)
+                    .unwrap();
+            for &chunk_size in &[1, 3, 8] {
+                let (frequent_itemsets, exceeded) =
+                    mine(chunk_size, min_support, 4, &MiningOptions::default()).unwrap();
+                assert_eq!(frequent_itemsets, expected);
+                assert_eq!(exceeded, None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_budget() {
+        let options = MiningOptions::default().with_budget(Budget {
+            max_candidates: Some(3),
+            max_memory: None,
+        });
+        let (frequent_itemsets, exceeded) = mine(8, 0.25, 3, &options).unwrap();
+
+        // 4 frequent items, 6 pairs
+        assert_eq!(exceeded.unwrap().level, 2);
+        assert_eq!(frequent_itemsets.len(), 1);
+        assert_eq!(frequent_itemsets[&1].len(), 4);
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert!(matches!(
+            mine(2, 1.5, 3, &MiningOptions::default()),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
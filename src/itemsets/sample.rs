@@ -0,0 +1,259 @@
+#![allow(non_snake_case)]
+
+use std::{collections::HashMap, time::Instant};
+
+use rand::{rngs::StdRng, seq::index, SeedableRng};
+
+use crate::{
+    error::{check_fraction, check_max_length, Error, Result},
+    itemsets::{
+        count::{
+            generate_frequent_itemsets_from_transactions_with_options,
+            generate_frequent_k_itemset_counts,
+        },
+        search::generate_candidates_from_prev,
+    },
+    progress::MiningOptions,
+    types::{
+        FrequentItemsets, ItemCounts, ItemId, Itemset, ItemsetCounts, ItemsetLength, Transaction,
+    },
+};
+
+/// Minimum support in the sample, as a fraction of the minimum support, if
+/// not given
+pub const DEFAULT_LOWERING: f32 = 0.8;
+
+/// How `generate_frequent_itemsets_sampled` draws and mines its sample
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampling {
+    /// No. of transactions drawn, without replacement
+    pub sample_size: usize,
+    pub seed: u64,
+    /// Minimum support in the sample. The lower it is, the less likely the
+    /// sample misses a frequent itemset, but the more itemsets are counted.
+    pub lowered_support: Option<f32>,
+}
+
+/// Generate frequent itemsets by sampling (Toivonen).
+///
+/// A random sample is mined at a lowered minimum support. Its frequent
+/// itemsets and their negative border, the itemsets not frequent in the
+/// sample whose every subset is, are then counted over all transactions.
+/// Returns the itemsets frequent over all transactions, and whether they are
+/// guaranteed to be all of them, which is the case if no itemset of the
+/// negative border is frequent.
+pub fn generate_frequent_itemsets_sampled(
+    transactions: &[Transaction],
+    min_support: f32,
+    k: ItemsetLength,
+    sampling: &Sampling,
+    options: &MiningOptions,
+) -> Result<(FrequentItemsets, bool)> {
+    check_fraction("min_support", min_support)?;
+    check_max_length(k)?;
+    let lowered_support = sampling
+        .lowered_support
+        .unwrap_or(min_support * DEFAULT_LOWERING);
+    check_fraction("lowered_support", lowered_support)?;
+    if lowered_support > min_support {
+        return Err(Error::InvalidParameter(String::from(
+            "lowered_support must not exceed min_support",
+        )));
+    }
+    if sampling.sample_size == 0 {
+        return Err(Error::InvalidParameter(String::from(
+            "sample size must be positive",
+        )));
+    }
+    let start = Instant::now();
+    let N = transactions.len();
+
+    // Sample, mined at the lowered support
+    let mut rng = StdRng::seed_from_u64(sampling.seed);
+    let sample: Vec<Transaction> = index::sample(&mut rng, N, sampling.sample_size.min(N))
+        .into_iter()
+        .map(|i| transactions[i].clone())
+        .collect();
+    // Levels are only complete after counting all transactions
+    let sample_options = MiningOptions {
+        progress: None,
+        ..options.clone()
+    };
+    let (sample_itemsets, exceeded) = generate_frequent_itemsets_from_transactions_with_options(
+        sample,
+        lowered_support,
+        k,
+        &sample_options,
+    )?;
+    let max_level = exceeded.as_ref().map_or(k, |e| e.level - 1);
+
+    // The sample's itemsets and negative border, counted over all transactions
+    let min_support_count = (min_support * N as f32).ceil() as u32;
+    let mut all_frequent_itemsets: FrequentItemsets = HashMap::with_capacity(max_level);
+    let mut exact = exceeded.is_none();
+    let empty = ItemsetCounts::new();
+    for size in 1..=max_level {
+        options.check()?;
+        let sample_level = sample_itemsets.get(&size).unwrap_or(&empty);
+        let counts: ItemsetCounts = if size == 1 {
+            // Every item is frequent in the sample or in its negative border
+            let mut item_counts: ItemCounts = HashMap::new();
+            for &item in transactions.iter().flatten() {
+                *item_counts.entry(item).or_insert(0) += 1;
+            }
+            item_counts
+                .into_iter()
+                .map(|(item, count)| (vec![item], count))
+                .collect()
+        } else {
+            let prev_sample_level = sample_itemsets.get(&(size - 1)).unwrap_or(&empty);
+            let candidates: Vec<Itemset> = generate_candidates_from_prev(prev_sample_level)?
+                .into_iter()
+                .filter(|candidate| all_subsets_in(candidate, prev_sample_level))
+                .collect();
+            generate_frequent_k_itemset_counts(candidates, transactions, 0, options)?
+        };
+        let num_candidates = counts.len();
+
+        let frequent_itemset_counts: ItemsetCounts = counts
+            .into_iter()
+            .filter(|&(_, count)| count >= min_support_count)
+            .collect();
+        if frequent_itemset_counts
+            .keys()
+            .any(|itemset| !sample_level.contains_key(itemset))
+        {
+            exact = false;
+        }
+        options.report(start, size, num_candidates, frequent_itemset_counts.len());
+        all_frequent_itemsets.insert(size, frequent_itemset_counts);
+    }
+
+    Ok((all_frequent_itemsets, exact))
+}
+
+/// Whether every subset of `itemset` one item smaller is in `itemsets`
+fn all_subsets_in(itemset: &[ItemId], itemsets: &ItemsetCounts) -> bool {
+    (0..itemset.len()).all(|i| {
+        let mut subset = itemset.to_vec();
+        subset.remove(i);
+        itemsets.contains_key(&subset)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::itemsets::count::generate_frequent_itemsets_from_transactions;
+
+    fn transactions() -> Vec<Transaction> {
+        (0..200)
+            .map(|i| match i % 4 {
+                0 => vec![0, 1, 2],
+                1 => vec![0, 1],
+                2 => vec![1, 2, 3],
+                _ => vec![i % 7 + 3],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exact() {
+        let transactions = transactions();
+        let expected =
+            generate_frequent_itemsets_from_transactions(transactions.clone(), 0.2, 3).unwrap();
+        let sampling = Sampling {
+            sample_size: 100,
+            seed: 42,
+            lowered_support: None,
+        };
+        let (frequent_itemsets, exact) = generate_frequent_itemsets_sampled(
+            &transactions,
+            0.2,
+            3,
+            &sampling,
+            &MiningOptions::default(),
+        )
+        .unwrap();
+
+        assert!(exact);
+        assert_eq!(frequent_itemsets, expected);
+    }
+
+    #[test]
+    fn test_not_exact() {
+        // A sample of either transaction misses the items of the other
+        let transactions = vec![vec![0, 1], vec![1, 2, 3]];
+        let expected =
+            generate_frequent_itemsets_from_transactions(transactions.clone(), 0.5, 2).unwrap();
+        let sampling = Sampling {
+            sample_size: 1,
+            seed: 0,
+            lowered_support: None,
+        };
+        let (frequent_itemsets, exact) = generate_frequent_itemsets_sampled(
+            &transactions,
+            0.5,
+            2,
+            &sampling,
+            &MiningOptions::default(),
+        )
+        .unwrap();
+
+        assert!(!exact);
+        assert_eq!(frequent_itemsets[&1], expected[&1]);
+        assert!(frequent_itemsets[&2].len() < expected[&2].len());
+        assert!(frequent_itemsets[&2]
+            .iter()
+            .all(|(itemset, count)| expected[&2][itemset] == *count));
+    }
+
+    #[test]
+    fn test_seed() {
+        let transactions = transactions();
+        let sampling = Sampling {
+            sample_size: 20,
+            seed: 7,
+            lowered_support: Some(0.1),
+        };
+        let mine = || {
+            generate_frequent_itemsets_sampled(
+                &transactions,
+                0.2,
+                3,
+                &sampling,
+                &MiningOptions::default(),
+            )
+            .unwrap()
+        };
+        assert_eq!(mine(), mine());
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let transactions = transactions();
+        let sampling = Sampling {
+            sample_size: 10,
+            seed: 0,
+            lowered_support: Some(0.3),
+        };
+        let mine = |sampling: Sampling| {
+            generate_frequent_itemsets_sampled(
+                &transactions,
+                0.2,
+                3,
+                &sampling,
+                &MiningOptions::default(),
+            )
+        };
+        assert!(matches!(mine(sampling), Err(Error::InvalidParameter(_))));
+        assert!(matches!(
+            mine(Sampling {
+                sample_size: 0,
+                lowered_support: None,
+                ..sampling
+            }),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
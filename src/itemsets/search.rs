@@ -1,20 +1,67 @@
 use itertools::Itertools;
 
-use crate::types::{ItemId, Itemset, ItemsetCounts};
+use crate::{
+    error::{Error, Result},
+    types::{ItemGroups, ItemId, Itemset, ItemsetCounts},
+};
 
 /// target k
-pub fn generate_candidates_from_prev(prev_frequent_itemsets: &ItemsetCounts) -> Vec<Itemset> {
+pub fn generate_candidates_from_prev(
+    prev_frequent_itemsets: &ItemsetCounts,
+) -> Result<Vec<Itemset>> {
     let curr: Vec<Itemset> = prev_frequent_itemsets.keys().cloned().collect();
     join_step(curr)
 }
 
-/// Join k length itemsets into k + 1 length itemsets.
-/// 
+/// Like `generate_candidates_from_prev`, without candidates that hold two
+/// items of the same group. The previous itemsets must not hold any.
+pub fn generate_candidates_excluding(
+    prev_frequent_itemsets: &ItemsetCounts,
+    groups: &ItemGroups,
+) -> Result<Vec<Itemset>> {
+    let curr: Vec<Itemset> = prev_frequent_itemsets.keys().cloned().collect();
+    // Joined itemsets only differ in their last items, the one new pair
+    join(curr, |a, b| match (groups.get(&a), groups.get(&b)) {
+        (Some(a), Some(b)) => a != b,
+        _ => true,
+    })
+}
+
+/// Number of candidates `generate_candidates_from_prev` would generate,
+/// counted without allocating them
+pub fn num_candidates_from_prev(prev_frequent_itemsets: &ItemsetCounts) -> usize {
+    let mut prefixes: Vec<&[ItemId]> = prev_frequent_itemsets
+        .keys()
+        .filter_map(|itemset| itemset.split_last())
+        .map(|(_, prefix)| prefix)
+        .collect();
+    prefixes.sort_unstable();
+    prefixes
+        .into_iter()
+        .dedup_with_count()
+        .map(|(n, _)| n * (n - 1) / 2)
+        .sum()
+}
+
+/// Join k length itemsets into k + 1 length itemsets. The itemsets must be
+/// non-empty and all of the same length.
+///
 /// Algorithm translated from
 /// https://github.com/tommyod/Efficient-Apriori/blob/master/efficient_apriori/itemsets.py
-pub fn join_step(mut itemsets: Vec<Itemset>) -> Vec<Itemset> {
+pub fn join_step(itemsets: Vec<Itemset>) -> Result<Vec<Itemset>> {
+    join(itemsets, |_, _| true)
+}
+
+/// `join_step`, keeping the candidates whose two last items pass `keep`
+fn join(mut itemsets: Vec<Itemset>, keep: impl Fn(ItemId, ItemId) -> bool) -> Result<Vec<Itemset>> {
     if itemsets.is_empty() {
-        return vec![];
+        return Ok(vec![]);
+    }
+    let k = itemsets[0].len();
+    if k == 0 || itemsets.iter().any(|itemset| itemset.len() != k) {
+        return Err(Error::InvalidParameter(String::from(
+            "itemsets to join must be non-empty and of the same length",
+        )));
     }
 
     itemsets.sort_unstable();
@@ -33,8 +80,8 @@ pub fn join_step(mut itemsets: Vec<Itemset>) -> Vec<Itemset> {
         tail_items.clear();
         tail_items.push(itemset_last);
 
-        for j in (i + 1)..itemsets.len() {
-            let (itemset_n_first, itemset_n_last) = itemsets[j].split_at(itemsets[j].len() - 1);
+        for itemset_n in &itemsets[(i + 1)..] {
+            let (itemset_n_first, itemset_n_last) = itemset_n.split_at(itemset_n.len() - 1);
             let itemset_n_last = itemset_n_last.to_owned().pop().unwrap();
 
             if itemset_first == itemset_n_first {
@@ -51,6 +98,9 @@ pub fn join_step(mut itemsets: Vec<Itemset>) -> Vec<Itemset> {
             let (a, b) = combi.split_at(1);
             let a = *a.to_owned().pop().unwrap();
             let b = *b.to_owned().pop().unwrap();
+            if !keep(a, b) {
+                continue;
+            }
 
             itemset_first_tuple.push(a);
             itemset_first_tuple.push(b);
@@ -61,7 +111,7 @@ pub fn join_step(mut itemsets: Vec<Itemset>) -> Vec<Itemset> {
         i += skip;
     }
 
-    final_itemsets
+    Ok(final_itemsets)
 }
 
 #[cfg(test)]
@@ -77,7 +127,7 @@ mod test {
             vec![1, 3, 5],
             vec![2, 3, 4],
         ];
-        let y = join_step(itemsets);
+        let y = join_step(itemsets).unwrap();
         assert_eq!(y.len(), 2);
         assert!(y.contains(&vec![1, 2, 3, 4]));
         assert!(y.contains(&vec![1, 3, 4, 5]));
@@ -87,7 +137,7 @@ mod test {
     fn test_join_step_2() {
         let itemsets: Vec<Itemset> =
             vec![vec![1, 2, 3], vec![1, 2, 4], vec![1, 3, 4], vec![2, 3, 4]];
-        let y = join_step(itemsets);
+        let y = join_step(itemsets).unwrap();
         assert!(!y.is_empty());
         assert!(y.contains(&vec![1, 2, 3, 4]));
     }
@@ -96,10 +146,56 @@ mod test {
     fn test_join_step_3() {
         let itemsets: Vec<Itemset> =
             vec![vec![1, 2], vec![2, 3], vec![1, 3], vec![1, 4], vec![3, 4]];
-        let y = join_step(itemsets);
+        let y = join_step(itemsets).unwrap();
         println!("{:?}", y);
         assert!(y.len() >= 2);
         assert!(y.contains(&vec![1, 2, 3]));
         assert!(y.contains(&vec![1, 3, 4]));
     }
+
+    #[test]
+    fn test_join_step_invalid() {
+        assert!(join_step(vec![]).unwrap().is_empty());
+        assert!(matches!(
+            join_step(vec![vec![]]),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            join_step(vec![vec![1, 2], vec![1]]),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_candidates_excluding() {
+        let itemsets: ItemsetCounts = vec![vec![1, 2], vec![1, 3], vec![1, 4], vec![2, 3]]
+            .into_iter()
+            .map(|itemset| (itemset, 1))
+            .collect();
+        // Items 2 and 3 are of the same group
+        let groups: ItemGroups = vec![(2, 0), (3, 0), (4, 1)].into_iter().collect();
+
+        let mut y = generate_candidates_excluding(&itemsets, &groups).unwrap();
+        y.sort_unstable();
+        assert_eq!(y, vec![vec![1, 2, 4], vec![1, 3, 4]]);
+    }
+
+    #[test]
+    fn test_num_candidates_from_prev() {
+        let itemsets: ItemsetCounts = vec![
+            vec![1, 2, 3],
+            vec![1, 2, 4],
+            vec![1, 3, 4],
+            vec![1, 3, 5],
+            vec![2, 3, 4],
+        ]
+        .into_iter()
+        .map(|itemset| (itemset, 1))
+        .collect();
+        assert_eq!(num_candidates_from_prev(&itemsets), 2);
+        assert_eq!(
+            num_candidates_from_prev(&itemsets),
+            generate_candidates_from_prev(&itemsets).unwrap().len()
+        );
+    }
 }
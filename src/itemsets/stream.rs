@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::{
+    error::{check_fraction, check_max_length, Error, Result},
+    types::{FrequentItemsets, ItemId, Itemset, ItemsetCounts, ItemsetLength, Transaction},
+};
+
+/// Buckets of transactions buffered and counted together. New itemsets are
+/// only tracked if they occur at least once per bucket of their batch.
+const BATCH_BUCKETS: usize = 8;
+
+#[derive(Clone)]
+struct Entry {
+    count: u32,
+    /// Maximum no. of occurrences missed before the entry was (re)inserted
+    delta: u32,
+}
+
+/// Tracked itemsets by size; `levels[k - 1]` holds those of `k` items
+type Levels = Vec<HashMap<Itemset, Entry>>;
+
+/// Approximate frequent itemsets over a stream of transactions (Lossy Counting,
+/// Manku & Motwani).
+///
+/// After `N` transactions, the count of every tracked itemset underestimates
+/// its true count by at most `epsilon * N`. Querying with a minimum support `s`
+/// returns every itemset whose true support is at least `s`, and no itemset
+/// whose true support is below `s - epsilon`.
+///
+/// Transactions are buffered and counted in batches of buckets, level by level
+/// (SetGen): an itemset of a transaction is only counted if all its subsets
+/// are still tracked.
+pub struct LossyCounter {
+    epsilon: f32,
+    max_length: ItemsetLength,
+    bucket_width: usize,
+    num_transactions: usize,
+    /// Buckets whose transactions are counted in `levels`
+    num_buckets: usize,
+    buffer: Vec<Transaction>,
+    levels: Levels,
+}
+
+impl LossyCounter {
+    pub fn new(epsilon: f32, max_length: ItemsetLength) -> Result<Self> {
+        if !(epsilon > 0.0 && epsilon < 1.0) {
+            return Err(Error::InvalidParameter(String::from(
+                "epsilon must be between 0 and 1",
+            )));
+        }
+        check_max_length(max_length)?;
+        Ok(Self {
+            epsilon,
+            max_length,
+            bucket_width: (1.0 / epsilon).ceil() as usize,
+            num_transactions: 0,
+            num_buckets: 0,
+            buffer: vec![],
+            levels: vec![HashMap::new(); max_length],
+        })
+    }
+
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    pub fn num_transactions(&self) -> usize {
+        self.num_transactions
+    }
+
+    /// No. of itemsets currently tracked, not counting those of buffered
+    /// transactions
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn add(&mut self, transaction: &[ItemId]) {
+        let mut items: Transaction = transaction.to_vec();
+        items.sort_unstable();
+        items.dedup();
+        self.buffer.push(items);
+        self.num_transactions += 1;
+
+        if self.num_transactions % (self.bucket_width * BATCH_BUCKETS) == 0 {
+            let bucket = self.current_bucket();
+            count_batch(
+                &mut self.levels,
+                &self.buffer,
+                bucket,
+                bucket - self.num_buckets,
+                true,
+            );
+            self.num_buckets = bucket;
+            self.buffer.clear();
+        }
+    }
+
+    pub fn extend<'a, I>(&mut self, transactions: I)
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        for transaction in transactions {
+            self.add(transaction);
+        }
+    }
+
+    /// Itemsets whose estimated count is at least `(min_support - epsilon) * N`,
+    /// including the buffered transactions.
+    ///
+    /// Itemsets are only reported if all their subsets are reported, so that
+    /// the result can be passed to `rules::search::generate_rules`.
+    /// `min_support` must be greater than `epsilon`.
+    pub fn frequent_itemsets(&self, min_support: f32) -> Result<FrequentItemsets> {
+        check_fraction("min_support", min_support)?;
+        if min_support <= self.epsilon {
+            return Err(Error::InvalidParameter(String::from(
+                "min_support must be greater than epsilon",
+            )));
+        }
+        let N = self.num_transactions as f32;
+        let min_count = ((min_support - self.epsilon) * N).max(0.0);
+
+        let mut levels = self.levels.clone();
+        if !self.buffer.is_empty() {
+            let bucket = self.current_bucket();
+            count_batch(
+                &mut levels,
+                &self.buffer,
+                bucket,
+                bucket - self.num_buckets,
+                false,
+            );
+        }
+
+        let mut by_size: FrequentItemsets = HashMap::with_capacity(self.max_length);
+        for (size, level) in (1..=self.max_length).zip(levels) {
+            let itemset_counts: ItemsetCounts = level
+                .into_iter()
+                .filter(|(_, entry)| entry.count as f32 >= min_count && entry.count > 0)
+                .map(|(itemset, entry)| (itemset, entry.count))
+                .collect();
+            by_size.insert(size, itemset_counts);
+        }
+
+        for size in 2..=self.max_length {
+            let (smaller, current) = (&by_size[&(size - 1)], &by_size[&size]);
+            let closed: ItemsetCounts = current
+                .iter()
+                .filter(|(itemset, _)| {
+                    itemset
+                        .iter()
+                        .copied()
+                        .combinations(size - 1)
+                        .all(|subset| smaller.contains_key(&subset))
+                })
+                .map(|(itemset, &count)| (itemset.to_owned(), count))
+                .collect();
+            by_size.insert(size, closed);
+        }
+
+        Ok(by_size)
+    }
+
+    fn current_bucket(&self) -> usize {
+        (self.num_transactions + self.bucket_width - 1) / self.bucket_width
+    }
+}
+
+/// Count the sorted transactions of a batch of `num_buckets` buckets ending
+/// with `bucket`, one itemset size at a time (SetGen). An itemset is counted
+/// only if its subsets one item smaller are tracked, and newly tracked if it
+/// occurs at least `num_buckets` times. With `prune`, tracked itemsets that
+/// can no longer be frequent are dropped, as are those with an untracked
+/// subset.
+fn count_batch(
+    levels: &mut Levels,
+    batch: &[Transaction],
+    bucket: usize,
+    num_buckets: usize,
+    prune: bool,
+) {
+    // Tracked itemsets of each transaction, of the previous size
+    let mut tracked: Vec<Vec<Itemset>> = vec![vec![vec![]]; batch.len()];
+    for size in 1..=levels.len() {
+        let (smaller, larger) = levels.split_at_mut(size - 1);
+        let (parents, level) = (smaller.last(), &mut larger[0]);
+
+        let mut counts: HashMap<Itemset, u32> = HashMap::new();
+        let mut candidates: Vec<Vec<Itemset>> = Vec::with_capacity(batch.len());
+        for (transaction, prefixes) in batch.iter().zip(&tracked) {
+            let mut transaction_candidates = vec![];
+            for prefix in prefixes {
+                let start = prefix
+                    .last()
+                    .map_or(0, |last| transaction.partition_point(|item| item <= last));
+                for &item in &transaction[start..] {
+                    let mut itemset = Vec::with_capacity(size);
+                    itemset.extend_from_slice(prefix);
+                    itemset.push(item);
+                    if parents.map_or(true, |parents| has_tracked_subsets(&itemset, parents)) {
+                        *counts.entry(itemset.clone()).or_insert(0) += 1;
+                        transaction_candidates.push(itemset);
+                    }
+                }
+            }
+            candidates.push(transaction_candidates);
+        }
+
+        let bucket = bucket as u32;
+        level.retain(|itemset, entry| {
+            if !parents.map_or(true, |parents| has_tracked_subsets(itemset, parents)) {
+                return false;
+            }
+            entry.count += counts.remove(itemset).unwrap_or(0);
+            !prune || entry.count + entry.delta > bucket
+        });
+        for (itemset, count) in counts {
+            if count as usize >= num_buckets {
+                let delta = bucket - num_buckets as u32;
+                level.insert(itemset, Entry { count, delta });
+            }
+        }
+
+        tracked = candidates
+            .into_iter()
+            .map(|mut itemsets| {
+                itemsets.retain(|itemset| level.contains_key(itemset));
+                itemsets
+            })
+            .collect();
+    }
+}
+
+/// Whether every subset of `itemset` one item smaller is in `parents`
+fn has_tracked_subsets(itemset: &[ItemId], parents: &HashMap<Itemset, Entry>) -> bool {
+    let mut subset = Vec::with_capacity(itemset.len() - 1);
+    (0..itemset.len()).all(|skip| {
+        subset.clear();
+        subset.extend_from_slice(&itemset[..skip]);
+        subset.extend_from_slice(&itemset[skip + 1..]);
+        parents.contains_key(&subset)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_exact_within_first_bucket() {
+        let mut counter = LossyCounter::new(0.1, 2).unwrap();
+        counter.extend(&vec![vec![1, 2], vec![2, 1], vec![1, 3]]);
+
+        let frequent_itemsets = counter.frequent_itemsets(0.5).unwrap();
+
+        assert_eq!(
+            frequent_itemsets,
+            hashmap! {
+                1 => hashmap! { vec![1] => 3, vec![2] => 2 },
+                2 => hashmap! { vec![1, 2] => 2 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_prunes_rare_itemsets() {
+        // One batch of 8 buckets of 4 transactions
+        let mut counter = LossyCounter::new(0.25, 1).unwrap();
+        for i in 0..32 {
+            counter.add(&[0, 100 + i]);
+        }
+
+        assert_eq!(counter.num_transactions(), 32);
+        assert_eq!(counter.len(), 1);
+        assert_eq!(
+            counter.frequent_itemsets(0.5).unwrap()[&1],
+            hashmap! { vec![0] => 32 }
+        );
+    }
+
+    #[test]
+    fn test_error_bound() {
+        let epsilon = 0.05;
+        let mut counter = LossyCounter::new(epsilon, 2).unwrap();
+        let transactions: Vec<Transaction> = (0..1000)
+            .map(|i| match i % 10 {
+                0..=5 => vec![1, 2],
+                6 | 7 => vec![1, 3],
+                _ => vec![i, i + 1],
+            })
+            .collect();
+        counter.extend(&transactions);
+
+        let frequent_itemsets = counter.frequent_itemsets(0.5).unwrap();
+        let N = 1000.0;
+
+        assert!(frequent_itemsets[&1][&vec![1]] as f32 >= (0.8 - epsilon) * N);
+        assert!(frequent_itemsets[&2][&vec![1, 2]] as f32 >= (0.6 - epsilon) * N);
+        assert!(!frequent_itemsets[&2].contains_key(&vec![1, 3]));
+    }
+
+    #[test]
+    fn test_wide_transactions() {
+        // Items 0 and 1 are in every transaction, with 20 rare ones
+        let epsilon = 0.01;
+        let mut counter = LossyCounter::new(epsilon, 3).unwrap();
+        let transactions: Vec<Transaction> = (0..2000)
+            .map(|i| {
+                let rare = (0..20).map(|j| 2 + (i * 20 + j) % 5000);
+                [0, 1].iter().copied().chain(rare).collect()
+            })
+            .collect();
+        counter.extend(&transactions);
+
+        // Far fewer than the 1,771 subsets of up to 3 items of a transaction
+        // times the transactions of a batch
+        assert!(counter.len() < 1000, "{}", counter.len());
+        let frequent_itemsets = counter.frequent_itemsets(0.5).unwrap();
+        assert_eq!(frequent_itemsets[&2], hashmap! { vec![0, 1] => 2000 });
+        assert!(frequent_itemsets[&3].is_empty());
+    }
+
+    #[test]
+    fn test_buffered_transactions() {
+        let mut counter = LossyCounter::new(0.01, 2).unwrap();
+        counter.extend(&vec![vec![1, 2]; 850]);
+
+        // 800 counted in a batch, 50 buffered
+        assert_eq!(counter.len(), 3);
+        assert_eq!(
+            counter.frequent_itemsets(0.5).unwrap()[&2],
+            hashmap! { vec![1, 2] => 850 }
+        );
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert!(LossyCounter::new(0.0, 2).is_err());
+        assert!(LossyCounter::new(0.1, 0).is_err());
+        assert!(LossyCounter::new(0.1, 2)
+            .unwrap()
+            .frequent_itemsets(2.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_min_support_not_above_epsilon() {
+        let mut counter = LossyCounter::new(0.1, 2).unwrap();
+        let mut transactions = vec![vec![1, 2], vec![1], vec![1]];
+        transactions.resize(11, vec![]);
+        counter.extend(&transactions);
+
+        // Every tracked itemset would pass a threshold of zero
+        assert!(matches!(
+            counter.frequent_itemsets(0.05),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(counter.frequent_itemsets(0.1).is_err());
+        assert_eq!(
+            counter.frequent_itemsets(0.2).unwrap()[&1],
+            hashmap! { vec![1] => 3 }
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use rayon::prelude::*;
+
+use crate::{
+    error::{Error, Result},
+    progress::MiningOptions,
+    types::{ItemId, Itemset, ItemsetCounts, Transaction},
+};
+
+/// Transactions counted between checks for cancellation
+const CHUNK_SIZE: usize = 1024;
+
+struct Node {
+    /// (item, index) pairs sorted by item. The index is of a node, or at the
+    /// last level of a candidate.
+    children: Vec<(ItemId, usize)>,
+}
+
+/// Candidates of the same size stored in a prefix trie, so that a transaction
+/// is counted against all of them in one walk over its subsets that share a
+/// prefix with some candidate.
+pub struct CandidateTrie {
+    k: usize,
+    nodes: Vec<Node>,
+    candidates: Vec<Itemset>,
+}
+
+impl CandidateTrie {
+    /// Build a trie of candidates, all of the same non-zero size. Duplicates
+    /// are counted once.
+    pub fn new(mut candidates: Vec<Itemset>) -> Result<Self> {
+        let k = candidates.first().map_or(0, |candidate| candidate.len());
+        if candidates
+            .iter()
+            .any(|candidate| candidate.is_empty() || candidate.len() != k)
+        {
+            return Err(Error::InvalidParameter(String::from(
+                "candidates must be non-empty and of the same size",
+            )));
+        }
+        for candidate in &mut candidates {
+            candidate.sort_unstable();
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        let mut nodes = vec![Node { children: vec![] }];
+
+        // Candidates are sorted, so a new child is always the last
+        for (index, candidate) in candidates.iter().enumerate() {
+            let mut node = 0;
+            for (depth, &item) in candidate.iter().enumerate() {
+                if depth == k - 1 {
+                    nodes[node].children.push((item, index));
+                    break;
+                }
+                node = match nodes[node].children.last() {
+                    Some(&(last, child)) if last == item => child,
+                    _ => {
+                        let child = nodes.len();
+                        nodes[node].children.push((item, child));
+                        nodes.push(Node { children: vec![] });
+                        child
+                    }
+                };
+            }
+        }
+
+        Ok(Self {
+            k,
+            nodes,
+            candidates,
+        })
+    }
+
+    /// Add 1 to the count of every candidate in `transaction`, which must be
+    /// sorted; see `count_all` for transactions that may not be
+    pub fn count(&self, transaction: &[ItemId], counts: &mut [u32]) {
+        debug_assert!(is_sorted(transaction), "unsorted transaction");
+        if self.k > 0 && transaction.len() >= self.k {
+            self.visit(0, 0, transaction, counts);
+        }
+    }
+
+    fn visit(&self, node: usize, depth: usize, items: &[ItemId], counts: &mut [u32]) {
+        let children = &self.nodes[node].children;
+        // Leave enough items for the rest of a candidate
+        let remaining = self.k - depth;
+        let end = items.len() + 1 - remaining;
+
+        let (mut i, mut j) = (0, 0);
+        while i < end && j < children.len() {
+            let (item, index) = children[j];
+            if items[i] < item {
+                i += 1;
+            } else if items[i] > item {
+                j += 1;
+            } else {
+                if remaining == 1 {
+                    counts[index] += 1;
+                } else {
+                    self.visit(index, depth + 1, &items[i + 1..], counts);
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    /// Count the candidates over all transactions, keeping those with at
+    /// least `min_support_count`. Unsorted transactions are counted as sorted
+    /// copies.
+    pub fn count_all(
+        self,
+        transactions: &[Transaction],
+        min_support_count: usize,
+        options: &MiningOptions,
+    ) -> Result<ItemsetCounts> {
+        let n = self.candidates.len();
+        let counts = transactions
+            .par_chunks(CHUNK_SIZE)
+            .try_fold(
+                || vec![0; n],
+                |mut counts, chunk| -> Result<Vec<u32>> {
+                    options.check()?;
+                    let mut sorted = Vec::new();
+                    for transaction in chunk {
+                        if is_sorted(transaction) {
+                            self.count(transaction, &mut counts);
+                        } else {
+                            sorted.clear();
+                            sorted.extend_from_slice(transaction);
+                            sorted.sort_unstable();
+                            self.count(&sorted, &mut counts);
+                        }
+                    }
+                    Ok(counts)
+                },
+            )
+            .try_reduce(
+                || vec![0; n],
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                    Ok(a)
+                },
+            )?;
+
+        Ok(self
+            .candidates
+            .into_iter()
+            .zip(counts)
+            .filter(|&(_, count)| count as usize >= min_support_count)
+            .collect())
+    }
+}
+
+fn is_sorted(items: &[ItemId]) -> bool {
+    items.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_count() {
+        let trie = CandidateTrie::new(vec![
+            vec![1, 2, 3],
+            vec![1, 2, 4],
+            vec![1, 3, 4],
+            vec![2, 3, 4],
+            vec![1, 2, 3],
+        ])
+        .unwrap();
+        let transactions = vec![
+            vec![1, 2, 3, 4],
+            vec![1, 2, 3],
+            vec![2, 3, 4, 5],
+            vec![1, 4],
+        ];
+
+        let counts = trie
+            .count_all(&transactions, 0, &MiningOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            counts,
+            hashmap! {
+                vec![1, 2, 3] => 2,
+                vec![1, 2, 4] => 1,
+                vec![1, 3, 4] => 1,
+                vec![2, 3, 4] => 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_min_support_count() {
+        let trie = CandidateTrie::new(vec![vec![0], vec![1], vec![2]]).unwrap();
+        let transactions = vec![vec![0, 1], vec![1, 2], vec![1]];
+
+        let counts = trie
+            .count_all(&transactions, 2, &MiningOptions::default())
+            .unwrap();
+
+        assert_eq!(counts, hashmap! { vec![1] => 3 });
+    }
+
+    #[test]
+    fn test_empty() {
+        let trie = CandidateTrie::new(vec![]).unwrap();
+
+        let counts = trie
+            .count_all(&[vec![0, 1]], 0, &MiningOptions::default())
+            .unwrap();
+
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn test_unsorted() {
+        let trie = CandidateTrie::new(vec![vec![3, 1], vec![2, 4]]).unwrap();
+        let transactions = vec![vec![4, 3, 2, 1], vec![1, 3], vec![4, 1, 2]];
+
+        let counts = trie
+            .count_all(&transactions, 0, &MiningOptions::default())
+            .unwrap();
+
+        assert_eq!(counts, hashmap! { vec![1, 3] => 2, vec![2, 4] => 2 });
+    }
+
+    #[test]
+    fn test_invalid_candidates() {
+        for candidates in [vec![vec![1, 2], vec![1]], vec![vec![]]] {
+            assert!(matches!(
+                CandidateTrie::new(candidates),
+                Err(Error::InvalidParameter(_))
+            ));
+        }
+    }
+}
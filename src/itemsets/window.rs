@@ -0,0 +1,376 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    error::{check_fraction, check_max_length, Error, Result},
+    types::{FrequentItemsets, ItemId, Itemset, ItemsetCounts, ItemsetLength, Transaction},
+};
+
+pub type Timestamp = f64;
+
+/// Which transactions are kept in a `SlidingWindow`.
+#[derive(Clone, Copy)]
+pub enum WindowSize {
+    /// The last N transactions
+    Count(usize),
+    /// Transactions whose timestamp is within the last T time units of the
+    /// latest transaction
+    Duration(Timestamp),
+}
+
+const ROOT: usize = 0;
+
+struct Node {
+    item: ItemId,
+    count: u32,
+    children: HashMap<ItemId, usize>,
+}
+
+/// Prefix tree of the transactions in the window with items in canonical
+/// (ascending) order (CanTree). The shape of the tree does not depend on item
+/// frequencies, so transactions are added and removed along a single path
+/// without restructuring it.
+struct CanTree {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+}
+
+impl CanTree {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                item: 0,
+                count: 0,
+                children: HashMap::new(),
+            }],
+            free: vec![],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes[ROOT].children.is_empty()
+    }
+
+    fn insert(&mut self, items: &[ItemId]) {
+        let mut node = ROOT;
+        for &item in items {
+            node = match self.nodes[node].children.get(&item) {
+                Some(&child) => child,
+                None => {
+                    let child = self.alloc(item);
+                    self.nodes[node].children.insert(item, child);
+                    child
+                }
+            };
+            self.nodes[node].count += 1;
+        }
+    }
+
+    /// Remove a transaction that was inserted before. The nodes whose count
+    /// drops to zero are the tail of its path, which is detached and reused.
+    fn remove(&mut self, items: &[ItemId]) {
+        let mut parent = ROOT;
+        for (i, &item) in items.iter().enumerate() {
+            let node = self.nodes[parent].children[&item];
+            self.nodes[node].count -= 1;
+            if self.nodes[node].count == 0 {
+                self.nodes[parent].children.remove(&item);
+                self.release(node, &items[i + 1..]);
+                return;
+            }
+            parent = node;
+        }
+    }
+
+    fn alloc(&mut self, item: ItemId) -> usize {
+        let node = Node {
+            item,
+            count: 0,
+            children: HashMap::new(),
+        };
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn release(&mut self, mut node: usize, items: &[ItemId]) {
+        for item in items {
+            self.free.push(node);
+            node = self.nodes[node].children[item];
+        }
+        self.free.push(node);
+    }
+
+    /// Pattern growth over the tree. `nodes` are the (disjoint) subtrees in
+    /// which `prefix` ends; in ascending order the itemsets extending `prefix`
+    /// by a larger item are found in those subtrees only.
+    fn mine(
+        &self,
+        nodes: &[usize],
+        prefix: &mut Itemset,
+        min_support_count: u32,
+        max_length: ItemsetLength,
+        all_frequent_itemsets: &mut FrequentItemsets,
+    ) {
+        let mut occurrences: HashMap<ItemId, (u32, Vec<usize>)> = HashMap::new();
+        let mut stack: Vec<usize> = nodes
+            .iter()
+            .flat_map(|&node| self.nodes[node].children.values().copied())
+            .collect();
+        while let Some(node) = stack.pop() {
+            let Node {
+                item,
+                count,
+                children,
+            } = &self.nodes[node];
+            let (support, item_nodes) = occurrences.entry(*item).or_default();
+            *support += count;
+            item_nodes.push(node);
+            stack.extend(children.values().copied());
+        }
+
+        for (item, (support, item_nodes)) in occurrences {
+            if support < min_support_count {
+                continue;
+            }
+            prefix.push(item);
+            all_frequent_itemsets
+                .get_mut(&prefix.len())
+                .unwrap()
+                .insert(prefix.clone(), support);
+            if prefix.len() < max_length {
+                self.mine(
+                    &item_nodes,
+                    prefix,
+                    min_support_count,
+                    max_length,
+                    all_frequent_itemsets,
+                );
+            }
+            prefix.pop();
+        }
+    }
+}
+
+/// Exact frequent itemsets over the most recent transactions of a stream.
+///
+/// The transactions in the window are kept in a CanTree, which shares common
+/// prefixes and is updated in time linear in the size of the transaction as
+/// transactions enter and leave the window. Querying mines the tree by pattern
+/// growth, visiting only frequent itemsets and their immediate extensions.
+pub struct SlidingWindow {
+    size: WindowSize,
+    max_length: ItemsetLength,
+    transactions: VecDeque<(Timestamp, Transaction)>,
+    tree: CanTree,
+}
+
+impl SlidingWindow {
+    pub fn new(size: WindowSize, max_length: ItemsetLength) -> Result<Self> {
+        let valid = match size {
+            WindowSize::Count(n) => n > 0,
+            WindowSize::Duration(t) => t > 0.0,
+        };
+        if !valid {
+            return Err(Error::InvalidParameter(String::from(
+                "window size must be positive",
+            )));
+        }
+        check_max_length(max_length)?;
+        Ok(Self {
+            size,
+            max_length,
+            transactions: VecDeque::new(),
+            tree: CanTree::new(),
+        })
+    }
+
+    /// No. of transactions in the window
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Add a transaction to the window and evict the transactions that fall
+    /// out of it. Timestamps are expected to be non-decreasing; they are
+    /// ignored for count-based windows.
+    pub fn add(&mut self, transaction: &[ItemId], timestamp: Timestamp) {
+        let mut items: Transaction = transaction.to_vec();
+        items.sort_unstable();
+        items.dedup();
+
+        self.tree.insert(&items);
+        self.transactions.push_back((timestamp, items));
+
+        self.evict(timestamp);
+    }
+
+    /// Evict the transactions that are older than `now` allows.
+    pub fn evict(&mut self, now: Timestamp) {
+        loop {
+            let expired = match (self.size, self.transactions.front()) {
+                (WindowSize::Count(n), Some(_)) => self.transactions.len() > n,
+                (WindowSize::Duration(t), Some((timestamp, _))) => *timestamp <= now - t,
+                (_, None) => false,
+            };
+            if !expired {
+                break;
+            }
+
+            let (_, items) = self.transactions.pop_front().unwrap();
+            self.tree.remove(&items);
+        }
+    }
+
+    /// Frequent itemsets of the transactions currently in the window
+    pub fn frequent_itemsets(&self, min_support: f32) -> Result<FrequentItemsets> {
+        check_fraction("min_support", min_support)?;
+        let N = self.transactions.len() as f32;
+        let min_support_count = ((min_support * N).ceil() as u32).max(1);
+
+        let mut all_frequent_itemsets: FrequentItemsets = (1..=self.max_length)
+            .map(|size| (size, ItemsetCounts::new()))
+            .collect();
+        self.tree.mine(
+            &[ROOT],
+            &mut vec![],
+            min_support_count,
+            self.max_length,
+            &mut all_frequent_itemsets,
+        );
+
+        Ok(all_frequent_itemsets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::itemsets::count::generate_frequent_itemsets_id;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_count_window() {
+        let mut window = SlidingWindow::new(WindowSize::Count(2), 2).unwrap();
+        window.add(&[1, 2], 0.0);
+        window.add(&[1, 3], 0.0);
+        window.add(&[3, 1], 0.0);
+
+        assert_eq!(window.len(), 2);
+        assert_eq!(
+            window.frequent_itemsets(1.0).unwrap(),
+            hashmap! {
+                1 => hashmap! { vec![1] => 2, vec![3] => 2 },
+                2 => hashmap! { vec![1, 3] => 2 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_duration_window() {
+        let mut window = SlidingWindow::new(WindowSize::Duration(10.0), 1).unwrap();
+        window.add(&[1], 0.0);
+        window.add(&[2], 5.0);
+        window.add(&[2], 10.0);
+
+        assert_eq!(window.len(), 2);
+        assert_eq!(
+            window.frequent_itemsets(1.0).unwrap(),
+            hashmap! { 1 => hashmap! { vec![2] => 2 } }
+        );
+
+        window.evict(100.0);
+        assert!(window.is_empty());
+        assert!(window.tree.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        for &size in &[
+            WindowSize::Count(0),
+            WindowSize::Duration(0.0),
+            WindowSize::Duration(-1.0),
+            WindowSize::Duration(f64::NAN),
+        ] {
+            assert!(matches!(
+                SlidingWindow::new(size, 1),
+                Err(Error::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_same_as_batch() {
+        let transactions: Vec<Transaction> = (0..50)
+            .map(|i| (0..6).filter(|j| (i * 7 + j * 3) % 5 < 3).collect())
+            .collect();
+        let mut window = SlidingWindow::new(WindowSize::Count(20), 3).unwrap();
+        for transaction in &transactions {
+            window.add(transaction, 0.0);
+        }
+
+        let expected = generate_frequent_itemsets_id(
+            transactions[30..]
+                .iter()
+                .map(|transaction| transaction.iter().copied().collect())
+                .collect(),
+            0.3,
+            3,
+        )
+        .unwrap();
+
+        assert_eq!(window.frequent_itemsets(0.3).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_wide_transactions() {
+        // Five common items, one in every other basket and 55 items of its own
+        // per basket: enumerating every subset up to length 8 would take
+        // billions of itemsets per basket.
+        let transactions: Vec<Transaction> = (0..60)
+            .map(|i| {
+                (0..5)
+                    .chain((i % 2 == 0).then_some(5))
+                    .chain(100 + i * 55..100 + (i + 1) * 55)
+                    .collect()
+            })
+            .collect();
+        let mut window = SlidingWindow::new(WindowSize::Count(10), 8).unwrap();
+        for transaction in &transactions {
+            window.add(transaction, 0.0);
+        }
+
+        let expected = generate_frequent_itemsets_id(
+            transactions[50..]
+                .iter()
+                .map(|transaction| transaction.iter().copied().collect())
+                .collect(),
+            0.5,
+            8,
+        )
+        .unwrap();
+        assert_eq!(window.frequent_itemsets(0.5).unwrap(), expected);
+        assert_eq!(
+            expected.values().map(|counts| counts.len()).sum::<usize>(),
+            63
+        );
+
+        window.size = WindowSize::Count(1);
+        window.evict(0.0);
+        assert_eq!(window.len(), 1);
+        assert_eq!(window.tree.nodes[ROOT].children.len(), 1);
+        assert_eq!(
+            window.tree.nodes.len() - window.tree.free.len(),
+            transactions[59].len() + 1
+        );
+    }
+}
@@ -1,24 +1,97 @@
 #![allow(dead_code,non_snake_case)]
+pub mod classify;
+pub mod error;
+pub mod interner;
+pub mod io;
 pub mod itemsets;
+pub mod model;
+pub mod progress;
+pub mod quantitative;
+pub mod recommend;
 pub mod rules;
 pub mod types;
 mod wrapper;
 
 use itemsets::count::__pyo3_get_function_generate_frequent_1_itemset_counts;
-use pyo3::types::PyDict;
+use pyo3::create_exception;
+use pyo3::exceptions::{
+    PyException, PyIOError, PyLookupError, PyRuntimeError, PyTimeoutError, PyValueError,
+};
+use pyo3::types::{PyBytes, PyDict};
 use pyo3::wrap_pyfunction;
-use pyo3::{prelude::*, PyObjectProtocol};
-use std::collections::{HashMap, HashSet};
-use types::{Inventory, PyFrequentItemsets, PyItemName, RawTransaction, RawTransactionId};
+use pyo3::{prelude::*, PyAsyncProtocol, PyObjectProtocol, PySequenceProtocol};
+use interner::Interner;
+use quantitative::{discretize::Discretization, NumericAttribute};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use types::{
+    Inventory, OwnedInventory, PyFrequentItemsets, PyItemName, RawTransaction, RawTransactionId,
+};
+
+create_exception!(apriori, InvalidParameterError, PyValueError);
+create_exception!(apriori, MissingCountError, PyLookupError);
+create_exception!(apriori, CancelledError, PyException);
+create_exception!(apriori, InvalidModelError, PyValueError);
+
+impl From<error::Error> for PyErr {
+    fn from(e: error::Error) -> PyErr {
+        PyErr::from(&e)
+    }
+}
+
+impl From<&error::Error> for PyErr {
+    fn from(e: &error::Error) -> PyErr {
+        let message = e.to_string();
+        match e {
+            error::Error::InvalidParameter(_) => InvalidParameterError::new_err(message),
+            error::Error::MissingCount(_) => MissingCountError::new_err(message),
+            error::Error::Io(_) => PyIOError::new_err(message),
+            error::Error::Cancelled => CancelledError::new_err(message),
+            error::Error::Format(_) | error::Error::Version(_) | error::Error::InvalidModel(_) => {
+                InvalidModelError::new_err(message)
+            }
+        }
+    }
+}
 
 fn main() {
     #[pymodule]
-    fn apriori(_: Python, m: &PyModule) -> PyResult<()> {
+    fn apriori(py: Python, m: &PyModule) -> PyResult<()> {
         m.add_function(wrap_pyfunction!(apriori, m)?)?;
+        m.add_function(wrap_pyfunction!(apriori_async, m)?)?;
+        m.add_function(wrap_pyfunction!(negative_apriori, m)?)?;
+        m.add_function(wrap_pyfunction!(quantitative_apriori, m)?)?;
+        m.add_function(wrap_pyfunction!(update_apriori, m)?)?;
+        m.add_function(wrap_pyfunction!(apriori_sampled, m)?)?;
+        m.add_function(wrap_pyfunction!(apriori_from_file, m)?)?;
+        m.add_function(wrap_pyfunction!(apriori_from_matrix, m)?)?;
         m.add_function(wrap_pyfunction!(generate_frequent_itemsets, m)?)?;
+        m.add_function(wrap_pyfunction!(update_frequent_itemsets, m)?)?;
+        m.add_function(wrap_pyfunction!(generate_frequent_itemsets_sampled, m)?)?;
+        m.add_function(wrap_pyfunction!(generate_frequent_itemsets_from_file, m)?)?;
+        m.add_function(wrap_pyfunction!(generate_frequent_itemsets_from_matrix, m)?)?;
         m.add_function(wrap_pyfunction!(generate_frequent_itemsets_id, m)?)?;
         m.add_function(wrap_pyfunction!(generate_frequent_1_itemset_counts, m)?)?;
+        m.add_function(wrap_pyfunction!(write_rules, m)?)?;
+        m.add_function(wrap_pyfunction!(write_itemsets, m)?)?;
+        m.add(
+            "InvalidParameterError",
+            py.get_type::<InvalidParameterError>(),
+        )?;
+        m.add("MissingCountError", py.get_type::<MissingCountError>())?;
+        m.add("CancelledError", py.get_type::<CancelledError>())?;
+        m.add("InvalidModelError", py.get_type::<InvalidModelError>())?;
         m.add_class::<Rule>()?;
+        m.add_class::<MiningJob>()?;
+        m.add_class::<CancellationToken>()?;
+        m.add_class::<LossyCounter>()?;
+        m.add_class::<SlidingWindow>()?;
+        m.add_class::<Model>()?;
+        m.add_class::<Recommender>()?;
+        m.add_class::<Classifier>()?;
         Ok(())
     }
 }
@@ -30,27 +103,292 @@ fn main() {
 ///     min_support (float): The minimum support.
 ///     min_confidence (float): The minimum confidence.
 ///     max_length (int): Maximum no. of items in an association rule.
+///     output (str): "python" for Python objects, or "arrow" for pyarrow RecordBatches.
+///     progress (Optional[Callable[[int, int, int, float], None]]): Called after each
+///         itemset size with the size, the no. of candidates, the no. of frequent itemsets
+///         and the seconds elapsed. If it raises, mining stops and the exception is raised.
+///     cancel (Optional[CancellationToken]): Stops mining when cancelled, raising
+///         `CancelledError`.
+///     max_candidates (Optional[int]): Maximum no. of candidates of one itemset size.
+///     max_memory (Optional[int]): Maximum estimated bytes of the candidates of one
+///         itemset size. If a size would exceed either budget, mining stops before it
+///         with a `RuntimeWarning`, and the results cover the smaller itemsets only.
+///     counting (str): How candidates are counted: "scan" checks each of 3 or more items
+///         against each transaction, "trie" walks each transaction once through a
+///         prefix trie of them, and "bitset" ANDs bitsets of the transactions
+///         containing each item, which suits dense transactions. "auto" uses "bitset"
+///         if the frequent items are dense, otherwise "trie". Bitsets over `max_memory`
+///         fall back to "trie". All give the same results.
+///     item_order (Optional[str]): Assigns item IDs by first appearance ("first"), by
+///         descending support ("frequency") or by name ("lexical"), so that the same
+///         transactions give the same IDs and output on every run. By default, IDs
+///         depend on hashing.
+///     relabel (Optional[str]): Relabels the frequent items in "descending" or "ascending"
+///         order of support before counting pairs, and drops the infrequent items from
+///         the transactions, which can speed up mining. Results keep the item IDs.
 ///
 /// Returns:
 ///     A tuple of (i) a list of association rules and (ii) frequent itemsets by size.
+///     With `output="arrow"`, a tuple of RecordBatches of (i) rules, with columns
+///     antecedent, consequent, support_count, support, confidence, lift,
+///     antecedent_support, consequent_support, leverage, conviction, negated_antecedent
+///     and negated_consequent, and (ii) itemsets, with columns itemset, support_count and
+///     support.
+#[pyfunction(
+    output = "\"python\"",
+    progress = "None",
+    cancel = "None",
+    max_candidates = "None",
+    max_memory = "None",
+    counting = "\"auto\"",
+    item_order = "None",
+    relabel = "None"
+)]
+#[pyo3(
+    text_signature = "(transactions, min_support, min_confidence, max_length, /, output=\"python\", progress=None, cancel=None, max_candidates=None, max_memory=None, counting=\"auto\", item_order=None, relabel=None)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn apriori(
+    py: Python,
+    raw_transactions: Vec<RawTransaction>,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    output: &str,
+    progress: Option<PyObject>,
+    cancel: Option<PyRef<CancellationToken>>,
+    max_candidates: Option<usize>,
+    max_memory: Option<usize>,
+    counting: &str,
+    item_order: Option<String>,
+    relabel: Option<String>,
+) -> PyResult<PyObject> {
+    let arrow = is_arrow(output)?;
+    let counting = parse_counting(counting)?;
+    let item_order = item_order.as_deref().map(parse_item_order).transpose()?;
+    let relabel = relabel.as_deref().map(parse_relabel).transpose()?;
+    let N = raw_transactions.len();
+    let hooks = wrapper::MiningHooks::new(progress, cancel.map(|cancel| cancel.token.clone()));
+    let report = |progress: &progress::Progress| hooks.report(progress);
+    let mut options = hooks
+        .options(&report)
+        .with_budget(progress::Budget {
+            max_candidates,
+            max_memory,
+        })
+        .with_counting(counting);
+    options.item_order = item_order;
+    options.relabel = relabel;
+    let result = py.allow_threads(|| {
+        let (itemset_counts, inventory, exceeded) =
+            itemsets::count::generate_frequent_itemsets_with_options(
+                raw_transactions,
+                min_support,
+                max_length,
+                &options,
+            )?;
+        let rules = rules::search::generate_rules_with_options(
+            &min_confidence,
+            &itemset_counts,
+            N,
+            &options,
+        )?;
+        Ok((itemset_counts, inventory, rules, exceeded))
+    });
+    let (itemset_counts, inventory, rules, exceeded) = hooks.result(py, result)?;
+    wrapper::warn_exceeded(py, exceeded.as_ref())?;
+
+    if arrow {
+        return Ok((
+            wrapper::rules_to_arrow(py, rules, &itemset_counts, &inventory, N)?,
+            wrapper::itemsets_to_arrow(py, &itemset_counts, &inventory, N)?,
+        )
+            .into_py(py));
+    }
+    Ok((
+        wrapper::convert_rules(rules, inventory),
+        wrapper::convert_itemset_counts(itemset_counts),
+    )
+        .into_py(py))
+}
+
+fn parse_counting(counting: &str) -> PyResult<itemsets::count::Counting> {
+    match counting {
+        "auto" => Ok(itemsets::count::Counting::Auto),
+        "scan" => Ok(itemsets::count::Counting::Scan),
+        "trie" => Ok(itemsets::count::Counting::Trie),
+        "bitset" => Ok(itemsets::count::Counting::Bitset),
+        _ => Err(PyValueError::new_err(
+            "counting must be one of \"auto\", \"scan\", \"trie\" or \"bitset\"",
+        )),
+    }
+}
+
+fn parse_item_order(item_order: &str) -> PyResult<itemsets::count::ItemOrder> {
+    match item_order {
+        "first" => Ok(itemsets::count::ItemOrder::FirstSeen),
+        "frequency" => Ok(itemsets::count::ItemOrder::Frequency),
+        "lexical" => Ok(itemsets::count::ItemOrder::Lexical),
+        _ => Err(PyValueError::new_err(
+            "item_order must be one of \"first\", \"frequency\" or \"lexical\"",
+        )),
+    }
+}
+
+fn parse_relabel(relabel: &str) -> PyResult<itemsets::count::SupportOrder> {
+    match relabel {
+        "descending" => Ok(itemsets::count::SupportOrder::Descending),
+        "ascending" => Ok(itemsets::count::SupportOrder::Ascending),
+        _ => Err(PyValueError::new_err(
+            "relabel must be one of \"descending\" or \"ascending\"",
+        )),
+    }
+}
+
+fn is_arrow(output: &str) -> PyResult<bool> {
+    match output {
+        "python" => Ok(false),
+        "arrow" => Ok(true),
+        _ => Err(PyValueError::new_err(
+            "output must be one of \"python\" or \"arrow\"",
+        )),
+    }
+}
+
+/// Apriori algorithm for negative association rules, i.e. rules of the form
+/// `A -> ¬B` and `¬A -> B`, where `A` and `B` are frequent and `A ∪ B` is a
+/// counted candidate, frequent or not.
+///
+/// Args:
+///     transactions (List[Set[str]]): A list of list of items.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+///
+/// Returns:
+///     A tuple of (i) a list of negative association rules and (ii) frequent itemsets by size.
 #[pyfunction]
 #[pyo3(text_signature = "(transactions, min_support, min_confidence, max_length, /)")]
-fn apriori(
+fn negative_apriori(
+    py: Python,
     raw_transactions: Vec<RawTransaction>,
     min_support: f32,
     min_confidence: f32,
     max_length: usize,
-) -> (Vec<Rule>, PyFrequentItemsets) {
+) -> PyResult<(Vec<Rule>, PyFrequentItemsets)> {
     let N = raw_transactions.len();
-    let (itemset_counts, inventory) =
-        itemsets::count::generate_frequent_itemsets(raw_transactions, min_support, max_length);
+    let (itemset_counts, inventory, rules) = py.allow_threads(|| {
+        let (itemset_counts, infrequent_counts, inventory) =
+            itemsets::count::generate_frequent_and_infrequent_itemsets(
+                raw_transactions,
+                min_support,
+                max_length,
+            )?;
+        let rules = rules::search::generate_negative_rules(
+            &min_confidence,
+            &itemset_counts,
+            &infrequent_counts,
+            N,
+        )?;
+        Ok::<_, error::Error>((itemset_counts, inventory, rules))
+    })?;
 
-    let rules = rules::search::generate_rules(&min_confidence, &itemset_counts, N);
+    Ok((
+        wrapper::convert_rules(rules, inventory),
+        wrapper::convert_itemset_counts(itemset_counts),
+    ))
+}
 
-    (
+/// Apriori algorithm for association rules over categorical items and
+/// intervals of numeric attributes, e.g. `age∈[25,34] ∧ bread -> milk`.
+///
+/// Args:
+///     transactions (List[Set[str]]): A list of list of items.
+///     numeric (Dict[str, List[float]]): Numeric attributes, with one value per transaction.
+///         Missing values are NaN.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+///     method (str): One of "equi-width", "equi-depth" or "supervised".
+///     bins (int): (Maximum) no. of base intervals per attribute.
+///     max_support (float): Maximum support of an interval merged from adjacent intervals.
+///     labels (Optional[List[str]]): Class label per transaction, required for "supervised".
+///
+/// Returns:
+///     A tuple of (i) a list of association rules and (ii) frequent itemsets by size.
+#[pyfunction(
+    method = "\"equi-depth\"",
+    bins = "4",
+    max_support = "0.5",
+    labels = "None"
+)]
+#[pyo3(
+    text_signature = "(transactions, numeric, min_support, min_confidence, max_length, method=\"equi-depth\", bins=4, max_support=0.5, labels=None)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn quantitative_apriori(
+    py: Python,
+    raw_transactions: Vec<RawTransaction>,
+    numeric: HashMap<&str, Vec<f64>>,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    method: &str,
+    bins: usize,
+    max_support: f32,
+    labels: Option<Vec<&str>>,
+) -> PyResult<(Vec<Rule>, PyFrequentItemsets)> {
+    let class_ids: Vec<usize> = {
+        let mut lookup = HashMap::new();
+        labels
+            .unwrap_or_default()
+            .into_iter()
+            .map(|label| {
+                let next_id = lookup.len();
+                *lookup.entry(label).or_insert(next_id)
+            })
+            .collect()
+    };
+    let discretization = match method {
+        "equi-width" => Discretization::EquiWidth(bins),
+        "equi-depth" => Discretization::EquiDepth(bins),
+        "supervised" if class_ids.len() == raw_transactions.len() => Discretization::Supervised {
+            labels: &class_ids,
+            max_bins: bins,
+        },
+        "supervised" => {
+            return Err(PyValueError::new_err(
+                "supervised discretization requires one label per transaction",
+            ))
+        }
+        _ => return Err(PyValueError::new_err(format!("unknown method: {}", method))),
+    };
+    let mut attributes: Vec<NumericAttribute> = numeric
+        .into_iter()
+        .map(|(name, values)| NumericAttribute { name, values })
+        .collect();
+    attributes.sort_unstable_by_key(|attribute| attribute.name);
+
+    let (rules, itemset_counts, inventory) = py.allow_threads(|| {
+        quantitative::generate_quantitative_rules(
+            raw_transactions,
+            &attributes,
+            &discretization,
+            min_support,
+            min_confidence,
+            max_length,
+            max_support,
+        )
+    })?;
+    let inventory: Inventory = inventory
+        .iter()
+        .map(|(&item_id, name)| (item_id, name.as_str()))
+        .collect();
+
+    Ok((
         wrapper::convert_rules(rules, inventory),
         wrapper::convert_itemset_counts(itemset_counts),
-    )
+    ))
 }
 
 /// Generate frequent itemsets from a list of transactions.
@@ -59,20 +397,78 @@ fn apriori(
 ///     transactions (List[Set[str]]): A list of list of items.
 ///     min_support (float): The minimum support.
 ///     max_length (int): Maximum no. of items in an association rule.
+///     output (str): "python" for Python objects, or "arrow" for a pyarrow RecordBatch.
+///     progress (Optional[Callable[[int, int, int, float], None]]): As for `apriori`.
+///     cancel (Optional[CancellationToken]): As for `apriori`.
+///     max_candidates (Optional[int]): As for `apriori`.
+///     max_memory (Optional[int]): As for `apriori`.
+///     counting (str): As for `apriori`.
+///     item_order (Optional[str]): As for `apriori`.
+///     relabel (Optional[str]): As for `apriori`.
 ///
 /// Returns:
 ///     A tuple of (i) frequent itemsets by size and (ii) a dictionary mapping of item ID to item name.
-#[pyfunction]
-#[pyo3(text_signature = "(transactions, min_support, max_length, /)")]
+///     With `output="arrow"`, a RecordBatch with columns itemset, support_count and support.
+#[pyfunction(
+    output = "\"python\"",
+    progress = "None",
+    cancel = "None",
+    max_candidates = "None",
+    max_memory = "None",
+    counting = "\"auto\"",
+    item_order = "None",
+    relabel = "None"
+)]
+#[pyo3(
+    text_signature = "(transactions, min_support, max_length, /, output=\"python\", progress=None, cancel=None, max_candidates=None, max_memory=None, counting=\"auto\", item_order=None, relabel=None)"
+)]
+#[allow(clippy::too_many_arguments)]
 fn generate_frequent_itemsets(
+    py: Python,
     raw_transactions: Vec<RawTransaction>,
     min_support: f32,
     max_length: usize,
-) -> (PyFrequentItemsets, Inventory) {
-    let (itemset_counts, inventory) =
-        itemsets::count::generate_frequent_itemsets(raw_transactions, min_support, max_length);
+    output: &str,
+    progress: Option<PyObject>,
+    cancel: Option<PyRef<CancellationToken>>,
+    max_candidates: Option<usize>,
+    max_memory: Option<usize>,
+    counting: &str,
+    item_order: Option<String>,
+    relabel: Option<String>,
+) -> PyResult<PyObject> {
+    let arrow = is_arrow(output)?;
+    let counting = parse_counting(counting)?;
+    let item_order = item_order.as_deref().map(parse_item_order).transpose()?;
+    let relabel = relabel.as_deref().map(parse_relabel).transpose()?;
+    let N = raw_transactions.len();
+    let hooks = wrapper::MiningHooks::new(progress, cancel.map(|cancel| cancel.token.clone()));
+    let report = |progress: &progress::Progress| hooks.report(progress);
+    let mut options = hooks
+        .options(&report)
+        .with_budget(progress::Budget {
+            max_candidates,
+            max_memory,
+        })
+        .with_counting(counting);
+    options.item_order = item_order;
+    options.relabel = relabel;
+    let result = py.allow_threads(|| {
+        itemsets::count::generate_frequent_itemsets_with_options(
+            raw_transactions,
+            min_support,
+            max_length,
+            &options,
+        )
+    });
+    let (itemset_counts, inventory, exceeded) = hooks.result(py, result)?;
+    wrapper::warn_exceeded(py, exceeded.as_ref())?;
 
-    (wrapper::convert_itemset_counts(itemset_counts), inventory)
+    if arrow {
+        return wrapper::itemsets_to_arrow(py, &itemset_counts, &inventory, N);
+    }
+    let id2item: BTreeMap<_, _> = inventory.into_iter().collect();
+    Ok((wrapper::convert_itemset_counts(itemset_counts), id2item).into_py(py))
 }
 
 /// Generate frequent itemsets from a list of transactions.
@@ -87,14 +483,606 @@ fn generate_frequent_itemsets(
 #[pyfunction]
 #[pyo3(text_signature = "(transactions, min_support, max_length, /)")]
 fn generate_frequent_itemsets_id(
+    py: Python,
     raw_transactions: Vec<RawTransactionId>,
     min_support: f32,
     max_length: usize,
-) -> Py<PyDict> {
-    let itemset_counts =
-        itemsets::count::generate_frequent_itemsets_id(raw_transactions, min_support, max_length);
+) -> PyResult<Py<PyDict>> {
+    let itemset_counts = py.allow_threads(|| {
+        itemsets::count::generate_frequent_itemsets_id(raw_transactions, min_support, max_length)
+    })?;
+
+    Ok(wrapper::convert_itemset_counts(itemset_counts))
+}
+
+/// Update frequent itemsets mined by `generate_frequent_itemsets` after
+/// transactions are added to and/or deleted from the data, without mining the
+/// data from scratch (FUP2).
+///
+/// Args:
+///     itemsets (Dict[int, Dict[FrozenSet[int], int]]): Previously mined frequent itemsets by size.
+///     id2item (Dict[int, str]): Previous mapping of item ID to item name.
+///     transactions (List[Set[str]]): The transactions the itemsets were mined from,
+///         including the deleted ones.
+///     added (List[Set[str]]): New transactions.
+///     deleted (List[Set[str]]): Transactions to remove.
+///     min_support (float): The minimum support the itemsets were mined with.
+///     max_length (int): Maximum no. of items in an itemset.
+///
+/// Returns:
+///     A tuple of (i) updated frequent itemsets by size and (ii) the updated mapping of item ID to item name.
+#[pyfunction]
+#[pyo3(
+    text_signature = "(itemsets, id2item, transactions, added, deleted, min_support, max_length, /)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn update_frequent_itemsets(
+    py: Python,
+    itemsets: &PyDict,
+    id2item: OwnedInventory,
+    raw_transactions: Vec<RawTransaction>,
+    added: Vec<RawTransaction>,
+    deleted: Vec<RawTransaction>,
+    min_support: f32,
+    max_length: usize,
+) -> PyResult<(PyFrequentItemsets, OwnedInventory)> {
+    let (itemset_counts, interner, _) = update(
+        py,
+        itemsets,
+        id2item,
+        raw_transactions,
+        added,
+        deleted,
+        min_support,
+        max_length,
+    )?;
+
+    Ok((
+        wrapper::convert_itemset_counts(itemset_counts),
+        interner.into_inventory(),
+    ))
+}
+
+/// Update association rules and frequent itemsets mined by
+/// `generate_frequent_itemsets` after transactions are added to and/or deleted
+/// from the data, without mining the data from scratch (FUP2).
+///
+/// Args:
+///     itemsets (Dict[int, Dict[FrozenSet[int], int]]): Previously mined frequent itemsets by size.
+///     id2item (Dict[int, str]): Previous mapping of item ID to item name.
+///     transactions (List[Set[str]]): The transactions the itemsets were mined from,
+///         including the deleted ones.
+///     added (List[Set[str]]): New transactions.
+///     deleted (List[Set[str]]): Transactions to remove.
+///     min_support (float): The minimum support the itemsets were mined with.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an itemset.
+///
+/// Returns:
+///     A tuple of (i) a list of association rules, (ii) updated frequent itemsets by size
+///     and (iii) the updated mapping of item ID to item name.
+#[pyfunction]
+#[pyo3(
+    text_signature = "(itemsets, id2item, transactions, added, deleted, min_support, min_confidence, max_length, /)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn update_apriori(
+    py: Python,
+    itemsets: &PyDict,
+    id2item: OwnedInventory,
+    raw_transactions: Vec<RawTransaction>,
+    added: Vec<RawTransaction>,
+    deleted: Vec<RawTransaction>,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+) -> PyResult<(Vec<Rule>, PyFrequentItemsets, OwnedInventory)> {
+    let (itemset_counts, interner, N) = update(
+        py,
+        itemsets,
+        id2item,
+        raw_transactions,
+        added,
+        deleted,
+        min_support,
+        max_length,
+    )?;
+
+    let rules =
+        py.allow_threads(|| rules::search::generate_rules(&min_confidence, &itemset_counts, N))?;
+
+    Ok((
+        wrapper::convert_rules(rules, interner.inventory()),
+        wrapper::convert_itemset_counts(itemset_counts),
+        interner.into_inventory(),
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update(
+    py: Python,
+    itemsets: &PyDict,
+    id2item: OwnedInventory,
+    raw_transactions: Vec<RawTransaction>,
+    added: Vec<RawTransaction>,
+    deleted: Vec<RawTransaction>,
+    min_support: f32,
+    max_length: usize,
+) -> PyResult<(types::FrequentItemsets, Interner, usize)> {
+    if deleted.len() > raw_transactions.len() {
+        return Err(PyValueError::new_err(
+            "cannot delete more transactions than there are",
+        ));
+    }
+    let previous = wrapper::extract_itemset_counts(itemsets)?;
+    let mut interner = Interner::from(id2item);
+    let transactions = wrapper::intern_transactions(raw_transactions, &mut interner);
+    let added = wrapper::intern_transactions(added, &mut interner);
+    let deleted = wrapper::intern_transactions(deleted, &mut interner);
+    let N = transactions.len() + added.len() - deleted.len();
+
+    let itemset_counts = py.allow_threads(|| {
+        itemsets::incremental::update_frequent_itemsets(
+            &previous,
+            &transactions,
+            &added,
+            &deleted,
+            min_support,
+            max_length,
+        )
+    })?;
+
+    Ok((itemset_counts, interner, N))
+}
+
+/// Approximate association rules by sampling (Toivonen): a random sample is
+/// mined at a lowered minimum support, and the result is verified against all
+/// transactions.
+///
+/// Args:
+///     transactions (List[Set[str]]): A list of list of items.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+///     sample_size (int): No. of transactions in the sample.
+///     seed (Optional[int]): Seed of the sample, random if not given.
+///     lowered_support (Optional[float]): Minimum support in the sample
+///         [default: 0.8 * min_support].
+///
+/// Returns:
+///     A tuple of (i) a list of association rules, (ii) frequent itemsets by size and
+///     (iii) whether these are guaranteed to be all of them. If not, the itemsets are
+///     still frequent, but some may be missing; mine again with a larger sample or a
+///     lower `lowered_support`.
+#[pyfunction(seed = "None", lowered_support = "None")]
+#[pyo3(
+    text_signature = "(transactions, min_support, min_confidence, max_length, sample_size, /, seed=None, lowered_support=None)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn apriori_sampled(
+    py: Python,
+    raw_transactions: Vec<RawTransaction>,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    sample_size: usize,
+    seed: Option<u64>,
+    lowered_support: Option<f32>,
+) -> PyResult<(Vec<Rule>, PyFrequentItemsets, bool)> {
+    let mut interner = Interner::new();
+    let transactions = wrapper::intern_transactions(raw_transactions, &mut interner);
+    let N = transactions.len();
+    let sampling = itemsets::sample::Sampling {
+        sample_size,
+        seed: seed.unwrap_or_else(rand::random),
+        lowered_support,
+    };
+    let (itemset_counts, exact, rules) = py.allow_threads(|| {
+        let (itemset_counts, exact) = itemsets::sample::generate_frequent_itemsets_sampled(
+            &transactions,
+            min_support,
+            max_length,
+            &sampling,
+            &progress::MiningOptions::default(),
+        )?;
+        let rules = rules::search::generate_rules(&min_confidence, &itemset_counts, N)?;
+        Ok::<_, error::Error>((itemset_counts, exact, rules))
+    })?;
+
+    Ok((
+        wrapper::convert_rules(rules, interner.inventory()),
+        wrapper::convert_itemset_counts(itemset_counts),
+        exact,
+    ))
+}
+
+/// Approximate frequent itemsets by sampling; see `apriori_sampled`.
+///
+/// Args:
+///     transactions (List[Set[str]]): A list of list of items.
+///     min_support (float): The minimum support.
+///     max_length (int): Maximum no. of items in an itemset.
+///     sample_size (int): No. of transactions in the sample.
+///     seed (Optional[int]): Seed of the sample, random if not given.
+///     lowered_support (Optional[float]): Minimum support in the sample
+///         [default: 0.8 * min_support].
+///
+/// Returns:
+///     A tuple of (i) frequent itemsets by size, (ii) a dictionary mapping of item ID to
+///     item name and (iii) whether the itemsets are guaranteed to be all of them.
+#[pyfunction(seed = "None", lowered_support = "None")]
+#[pyo3(
+    text_signature = "(transactions, min_support, max_length, sample_size, /, seed=None, lowered_support=None)"
+)]
+fn generate_frequent_itemsets_sampled(
+    py: Python,
+    raw_transactions: Vec<RawTransaction>,
+    min_support: f32,
+    max_length: usize,
+    sample_size: usize,
+    seed: Option<u64>,
+    lowered_support: Option<f32>,
+) -> PyResult<(PyFrequentItemsets, OwnedInventory, bool)> {
+    let mut interner = Interner::new();
+    let transactions = wrapper::intern_transactions(raw_transactions, &mut interner);
+    let sampling = itemsets::sample::Sampling {
+        sample_size,
+        seed: seed.unwrap_or_else(rand::random),
+        lowered_support,
+    };
+    let (itemset_counts, exact) = py.allow_threads(|| {
+        itemsets::sample::generate_frequent_itemsets_sampled(
+            &transactions,
+            min_support,
+            max_length,
+            &sampling,
+            &progress::MiningOptions::default(),
+        )
+    })?;
+
+    Ok((
+        wrapper::convert_itemset_counts(itemset_counts),
+        interner.into_inventory(),
+        exact,
+    ))
+}
+
+/// Apriori algorithm for association rules over transactions read from a file,
+/// without building them in Python first.
+///
+/// Args:
+///     path (str): Path to the file.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+///     format (str): One of "basket" (one transaction per line), "spmf" (SPMF
+///         integer format) or "long" (one `transaction_id,item` pair per line).
+///     delimiter (str): Item delimiter for "basket", field delimiter for "long".
+///     header (bool): Whether the first line of a "long" file is a header.
+///     chunk_size (Optional[int]): If given, the file is read twice in chunks of this
+///         many transactions and mined by the partition (SON) algorithm, so that it
+///         need not fit in memory. Not supported for "long".
+///
+/// Returns:
+///     A tuple of (i) a list of association rules and (ii) frequent itemsets by size.
+#[pyfunction(
+    format = "\"basket\"",
+    delimiter = "\",\"",
+    header = "true",
+    chunk_size = "None"
+)]
+#[pyo3(
+    text_signature = "(path, min_support, min_confidence, max_length, format=\"basket\", delimiter=\",\", header=True, chunk_size=None)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn apriori_from_file(
+    py: Python,
+    path: &str,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    format: &str,
+    delimiter: &str,
+    header: bool,
+    chunk_size: Option<usize>,
+) -> PyResult<(Vec<Rule>, PyFrequentItemsets)> {
+    let format = input_format(format, delimiter, header)?;
+    let (itemset_counts, interner, rules) = py.allow_threads(|| {
+        let (itemset_counts, interner, N) =
+            mine_file(path, &format, min_support, max_length, chunk_size)?;
+        let rules = rules::search::generate_rules(&min_confidence, &itemset_counts, N)?;
+        Ok::<_, error::Error>((itemset_counts, interner, rules))
+    })?;
+
+    Ok((
+        wrapper::convert_rules(rules, interner.inventory()),
+        wrapper::convert_itemset_counts(itemset_counts),
+    ))
+}
+
+/// Generate frequent itemsets from transactions read from a file.
+///
+/// Args:
+///     path (str): Path to the file.
+///     min_support (float): The minimum support.
+///     max_length (int): Maximum no. of items in an itemset.
+///     format (str): One of "basket", "spmf" or "long"; see `apriori_from_file`.
+///     delimiter (str): Item delimiter for "basket", field delimiter for "long".
+///     header (bool): Whether the first line of a "long" file is a header.
+///     chunk_size (Optional[int]): As for `apriori_from_file`.
+///
+/// Returns:
+///     A tuple of (i) frequent itemsets by size and (ii) a dictionary mapping of item ID to item name.
+#[pyfunction(
+    format = "\"basket\"",
+    delimiter = "\",\"",
+    header = "true",
+    chunk_size = "None"
+)]
+#[pyo3(
+    text_signature = "(path, min_support, max_length, format=\"basket\", delimiter=\",\", header=True, chunk_size=None)"
+)]
+#[allow(clippy::too_many_arguments)]
+fn generate_frequent_itemsets_from_file(
+    py: Python,
+    path: &str,
+    min_support: f32,
+    max_length: usize,
+    format: &str,
+    delimiter: &str,
+    header: bool,
+    chunk_size: Option<usize>,
+) -> PyResult<(PyFrequentItemsets, OwnedInventory)> {
+    let format = input_format(format, delimiter, header)?;
+    let (itemset_counts, interner, _) =
+        py.allow_threads(|| mine_file(path, &format, min_support, max_length, chunk_size))?;
+
+    Ok((
+        wrapper::convert_itemset_counts(itemset_counts),
+        interner.into_inventory(),
+    ))
+}
+
+/// Apriori algorithm for association rules over a one-hot matrix, without
+/// converting it to a list of sets first.
+///
+/// Args:
+///     matrix: A 2-D numpy bool or uint8 array, a pandas DataFrame of bool or uint8
+///         columns (e.g. the output of mlxtend's `TransactionEncoder`), or a scipy
+///         sparse matrix. Rows are transactions and columns are items.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+///     columns (Optional[List[str]]): Item name of each column. Defaults to the
+///         columns of a DataFrame, else the column indices.
+///     long_columns (Optional[Tuple[str, str]]): The (transaction, item) columns of a
+///         long DataFrame, with a row per item of a transaction, given instead of a
+///         one-hot matrix. Items are named by their values.
+///
+/// Returns:
+///     A tuple of (i) a list of association rules and (ii) frequent itemsets by size.
+#[pyfunction(columns = "None", long_columns = "None")]
+#[pyo3(
+    text_signature = "(matrix, min_support, min_confidence, max_length, columns=None, long_columns=None)"
+)]
+fn apriori_from_matrix(
+    py: Python,
+    matrix: &PyAny,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    columns: Option<Vec<&PyAny>>,
+    long_columns: Option<(&PyAny, &PyAny)>,
+) -> PyResult<(Vec<Rule>, PyFrequentItemsets)> {
+    let (transactions, id2item) = wrapper::extract_matrix(matrix, columns, long_columns)?;
+    let N = transactions.len();
+    let (itemset_counts, rules) = py.allow_threads(|| {
+        let itemset_counts = itemsets::count::generate_frequent_itemsets_from_transactions(
+            transactions,
+            min_support,
+            max_length,
+        )?;
+        let rules = rules::search::generate_rules(&min_confidence, &itemset_counts, N)?;
+        Ok::<_, error::Error>((itemset_counts, rules))
+    })?;
+    let inventory: Inventory = id2item
+        .iter()
+        .map(|(&item_id, name)| (item_id, name.as_str()))
+        .collect();
+
+    Ok((
+        wrapper::convert_rules(rules, inventory),
+        wrapper::convert_itemset_counts(itemset_counts),
+    ))
+}
+
+/// Generate frequent itemsets from a one-hot matrix.
+///
+/// Args:
+///     matrix: A 2-D numpy bool or uint8 array, a pandas DataFrame of such columns,
+///         or a scipy sparse matrix; see `apriori_from_matrix`.
+///     min_support (float): The minimum support.
+///     max_length (int): Maximum no. of items in an itemset.
+///     columns (Optional[List[str]]): Item name of each column.
+///     long_columns (Optional[Tuple[str, str]]): The (transaction, item) columns of a
+///         long DataFrame; see `apriori_from_matrix`.
+///
+/// Returns:
+///     A tuple of (i) frequent itemsets by size and (ii) a dictionary mapping of item ID
+///     (column index) to item name.
+#[pyfunction(columns = "None", long_columns = "None")]
+#[pyo3(text_signature = "(matrix, min_support, max_length, columns=None, long_columns=None)")]
+fn generate_frequent_itemsets_from_matrix(
+    py: Python,
+    matrix: &PyAny,
+    min_support: f32,
+    max_length: usize,
+    columns: Option<Vec<&PyAny>>,
+    long_columns: Option<(&PyAny, &PyAny)>,
+) -> PyResult<(PyFrequentItemsets, OwnedInventory)> {
+    let (transactions, id2item) = wrapper::extract_matrix(matrix, columns, long_columns)?;
+    let itemset_counts = py.allow_threads(|| {
+        itemsets::count::generate_frequent_itemsets_from_transactions(
+            transactions,
+            min_support,
+            max_length,
+        )
+    })?;
+
+    Ok((wrapper::convert_itemset_counts(itemset_counts), id2item))
+}
+
+/// Mine the file at `path`, read all at once, or in chunks of `chunk_size`
+/// transactions by the partition algorithm. Returns the no. of transactions
+/// as well.
+fn mine_file(
+    path: &str,
+    format: &io::read::Format,
+    min_support: f32,
+    max_length: usize,
+    chunk_size: Option<usize>,
+) -> error::Result<(types::FrequentItemsets, Interner, usize)> {
+    match chunk_size {
+        Some(chunk_size) => {
+            let mut interner = Interner::new();
+            let (itemset_counts, N, _) = itemsets::partition::generate_frequent_itemsets_from_file(
+                path,
+                format,
+                chunk_size,
+                &mut interner,
+                min_support,
+                max_length,
+                &progress::MiningOptions::default(),
+            )?;
+            Ok((itemset_counts, interner, N))
+        }
+        None => {
+            let (transactions, interner) = io::read::read_transactions(path, format)?;
+            let N = transactions.len();
+            let itemset_counts = itemsets::count::generate_frequent_itemsets_from_transactions(
+                transactions,
+                min_support,
+                max_length,
+            )?;
+            Ok((itemset_counts, interner, N))
+        }
+    }
+}
 
-    wrapper::convert_itemset_counts(itemset_counts)
+fn input_format(format: &str, delimiter: &str, header: bool) -> PyResult<io::read::Format> {
+    let mut chars = delimiter.chars();
+    let delimiter = match (chars.next(), chars.next()) {
+        (Some(delimiter), None) => delimiter,
+        _ => {
+            return Err(PyValueError::new_err(
+                "delimiter must be a single character",
+            ))
+        }
+    };
+    let format = match format {
+        "basket" => io::read::Format::Basket { delimiter },
+        "spmf" => io::read::Format::Spmf,
+        "long" => io::read::Format::Long {
+            delimiter,
+            has_header: header,
+        },
+        _ => {
+            return Err(PyValueError::new_err(
+                "format must be one of \"basket\", \"spmf\" or \"long\"",
+            ))
+        }
+    };
+    Ok(format)
+}
+
+/// Write association rules to a file.
+///
+/// Args:
+///     rules (List[Rule]): Association rules.
+///     path (str): Path to the file.
+///     num_transactions (int): No. of transactions the rules were mined from,
+///         to convert supports to counts.
+///     format (str): One of "spmf", "csv" or "jsonl".
+#[pyfunction(format = "\"csv\"")]
+#[pyo3(text_signature = "(rules, path, num_transactions, format=\"csv\")")]
+fn write_rules(
+    pyrules: Vec<PyRef<Rule>>,
+    path: &str,
+    num_transactions: usize,
+    format: &str,
+) -> PyResult<()> {
+    let format = output_format(format)?;
+    let mut interner = Interner::new();
+    let rules: Vec<rules::rule::Rule> = pyrules
+        .iter()
+        .map(|pyrule| wrapper::extract_rule(pyrule, &mut interner))
+        .collect();
+
+    let file = std::fs::File::create(path).map_err(PyIOError::new_err)?;
+    io::write::write_rules(
+        std::io::BufWriter::new(file),
+        &rules,
+        &interner.inventory(),
+        num_transactions,
+        format,
+    )
+    .map_err(PyIOError::new_err)
+}
+
+/// Write frequent itemsets to a file.
+///
+/// Args:
+///     itemsets (Dict[int, Dict[FrozenSet[int], int]]): Frequent itemsets by size.
+///     id2item (Optional[Dict[int, str]]): Mapping of item ID to item name. If None,
+///         items are written as their IDs.
+///     path (str): Path to the file.
+///     num_transactions (int): No. of transactions the itemsets were mined from,
+///         to convert counts to supports.
+///     format (str): One of "spmf", "csv" or "jsonl".
+#[pyfunction(format = "\"spmf\"")]
+#[pyo3(text_signature = "(itemsets, id2item, path, num_transactions, format=\"spmf\")")]
+fn write_itemsets(
+    itemsets: &PyDict,
+    id2item: Option<OwnedInventory>,
+    path: &str,
+    num_transactions: usize,
+    format: &str,
+) -> PyResult<()> {
+    let format = output_format(format)?;
+    let itemset_counts = wrapper::extract_itemset_counts(itemsets)?;
+    let id2item = id2item.unwrap_or_else(|| {
+        itemset_counts
+            .values()
+            .flat_map(|counts| counts.keys())
+            .flatten()
+            .map(|&item_id| (item_id, item_id.to_string()))
+            .collect()
+    });
+    let inventory: Inventory = id2item
+        .iter()
+        .map(|(&item_id, name)| (item_id, name.as_str()))
+        .collect();
+
+    let file = std::fs::File::create(path).map_err(PyIOError::new_err)?;
+    io::write::write_itemsets(
+        std::io::BufWriter::new(file),
+        &itemset_counts,
+        &inventory,
+        num_transactions,
+        format,
+    )
+    .map_err(PyIOError::new_err)
+}
+
+fn output_format(format: &str) -> PyResult<io::write::OutputFormat> {
+    match format {
+        "spmf" => Ok(io::write::OutputFormat::Spmf),
+        "csv" => Ok(io::write::OutputFormat::Csv),
+        "jsonl" => Ok(io::write::OutputFormat::JsonLines),
+        _ => Err(PyValueError::new_err(
+            "format must be one of \"spmf\", \"csv\" or \"jsonl\"",
+        )),
+    }
 }
 
 #[pyclass]
@@ -104,14 +1092,765 @@ pub struct Rule {
     #[pyo3(get)]
     consequent: HashSet<PyItemName>,
     #[pyo3(get)]
+    support: f32,
+    #[pyo3(get)]
     confidence: f32,
     #[pyo3(get)]
     lift: f32,
+    #[pyo3(get)]
+    negated_antecedent: bool,
+    #[pyo3(get)]
+    negated_consequent: bool,
 }
 
 #[pyproto]
 impl PyObjectProtocol for Rule {
     fn __repr__(&self) -> PyResult<String> {
-        Ok(format!("{:?} -> {:?}", &self.antecedent, &self.consequent))
+        Ok(format!(
+            "{}{:?} -> {}{:?}",
+            if self.negated_antecedent { "¬" } else { "" },
+            &self.antecedent,
+            if self.negated_consequent { "¬" } else { "" },
+            &self.consequent
+        ))
+    }
+}
+
+/// Apriori algorithm for association rules, run in a background thread.
+///
+/// The transactions are converted before returning, so they can be modified
+/// afterwards.
+///
+/// Args:
+///     transactions (List[Set[str]]): A list of list of items.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+///     progress (Optional[Callable[[int, int, int, float], None]]): As for `apriori`,
+///         called from the mining thread.
+///     max_candidates (Optional[int]): As for `apriori`.
+///     max_memory (Optional[int]): As for `apriori`; the warning is issued by `result()`.
+///
+/// Returns:
+///     A `MiningJob`, whose result is that of `apriori`. The job can be polled
+///     with `done()`, waited for with `result()`, awaited, or cancelled.
+#[pyfunction(progress = "None", max_candidates = "None", max_memory = "None")]
+#[pyo3(
+    text_signature = "(transactions, min_support, min_confidence, max_length, /, progress=None, max_candidates=None, max_memory=None)"
+)]
+fn apriori_async(
+    raw_transactions: Vec<RawTransaction>,
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    progress: Option<PyObject>,
+    max_candidates: Option<usize>,
+    max_memory: Option<usize>,
+) -> MiningJob {
+    let mut interner = Interner::new();
+    let transactions = wrapper::intern_transactions(raw_transactions, &mut interner);
+    let state: Arc<JobState> = Arc::default();
+    let hooks = wrapper::MiningHooks::new(progress, None);
+    let token = hooks.token.clone();
+
+    let job_state = Arc::clone(&state);
+    thread::spawn(move || {
+        let result = match panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let report = |progress: &progress::Progress| hooks.report(progress);
+            let options = hooks.options(&report).with_budget(progress::Budget {
+                max_candidates,
+                max_memory,
+            });
+            let N = transactions.len();
+            let (itemset_counts, exceeded) =
+                itemsets::count::generate_frequent_itemsets_from_transactions_with_options(
+                    transactions,
+                    min_support,
+                    max_length,
+                    &options,
+                )?;
+            let rules = rules::search::generate_rules_with_options(
+                &min_confidence,
+                &itemset_counts,
+                N,
+                &options,
+            )?;
+            Ok((rules, itemset_counts, exceeded))
+        })) {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(e)) => Err(match (e, hooks.take_error()) {
+                (error::Error::Cancelled, Some(error)) => JobError::Raised(error),
+                (e, _) => JobError::Mining(Arc::new(e)),
+            }),
+            Err(_) => Err(JobError::Panicked),
+        };
+        let (result_slot, finished) = &*job_state;
+        *result_slot.lock().unwrap() = Some(result);
+        finished.notify_all();
+    });
+
+    MiningJob {
+        state,
+        interner,
+        token,
+    }
+}
+
+#[derive(Clone)]
+enum JobError {
+    Panicked,
+    Mining(Arc<error::Error>),
+    /// The progress callback raised this exception
+    Raised(PyObject),
+}
+
+type JobResult = Result<
+    (
+        Vec<rules::rule::Rule>,
+        types::FrequentItemsets,
+        Option<progress::BudgetExceeded>,
+    ),
+    JobError,
+>;
+type JobState = (Mutex<Option<JobResult>>, Condvar);
+
+/// A mining run in a background thread; see `apriori_async`.
+#[pyclass]
+pub struct MiningJob {
+    state: Arc<JobState>,
+    interner: Interner,
+    token: progress::CancellationToken,
+}
+
+#[pymethods]
+impl MiningJob {
+    /// Whether mining has finished.
+    #[pyo3(text_signature = "($self, /)")]
+    fn done(&self) -> bool {
+        self.state.0.lock().unwrap().is_some()
+    }
+
+    /// Stop mining; `result()` then raises `CancelledError`. Has no effect if
+    /// mining has finished.
+    #[pyo3(text_signature = "($self, /)")]
+    fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Wait for mining to finish, without holding the GIL.
+    ///
+    /// Args:
+    ///     timeout (Optional[float]): Maximum no. of seconds to wait.
+    ///
+    /// Returns:
+    ///     A tuple of (i) a list of association rules and (ii) frequent itemsets by size.
+    #[pyo3(text_signature = "($self, timeout=None)")]
+    fn result(
+        &self,
+        py: Python,
+        timeout: Option<f64>,
+    ) -> PyResult<(Vec<Rule>, PyFrequentItemsets)> {
+        let state = Arc::clone(&self.state);
+        let result = py.allow_threads(move || {
+            let (result_slot, finished) = &*state;
+            let result_slot = result_slot.lock().unwrap();
+            let result_slot = match timeout {
+                Some(timeout) => {
+                    finished
+                        .wait_timeout_while(
+                            result_slot,
+                            Duration::from_secs_f64(timeout.max(0.0)),
+                            |result| result.is_none(),
+                        )
+                        .unwrap()
+                        .0
+                }
+                None => finished
+                    .wait_while(result_slot, |result| result.is_none())
+                    .unwrap(),
+            };
+            result_slot.clone()
+        });
+
+        match result {
+            None => Err(PyTimeoutError::new_err("mining has not finished")),
+            Some(Err(JobError::Panicked)) => Err(PyRuntimeError::new_err("mining failed")),
+            Some(Err(JobError::Mining(e))) => Err(PyErr::from(&*e)),
+            Some(Err(JobError::Raised(error))) => Err(PyErr::from_instance(error.as_ref(py))),
+            Some(Ok((rules, itemset_counts, exceeded))) => {
+                wrapper::warn_exceeded(py, exceeded.as_ref())?;
+                Ok((
+                    wrapper::convert_rules(rules, self.interner.inventory()),
+                    wrapper::convert_itemset_counts(itemset_counts),
+                ))
+            }
+        }
+    }
+}
+
+#[pyproto]
+impl PyAsyncProtocol for MiningJob {
+    /// Wait for the result in the event loop's default executor
+    fn __await__(slf: PyRef<Self>) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let result = slf.into_py(py).getattr(py, "result")?;
+            let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+            let future = event_loop.call_method1("run_in_executor", (py.None(), result))?;
+            Ok(future.call_method0("__await__")?.into())
+        })
+    }
+}
+
+/// A flag to stop `apriori` or `generate_frequent_itemsets` from another
+/// thread, after which they raise `CancelledError`.
+#[pyclass]
+#[pyo3(text_signature = "(/)")]
+#[derive(Default)]
+pub struct CancellationToken {
+    token: progress::CancellationToken,
+}
+
+#[pymethods]
+impl CancellationToken {
+    #[new]
+    fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Stop the runs using this token.
+    #[pyo3(text_signature = "($self, /)")]
+    fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether `cancel()` has been called.
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// Approximate frequent itemsets over a stream of transactions (Lossy Counting).
+///
+/// Args:
+///     epsilon (float): Maximum error in support of the reported itemsets.
+///     max_length (int): Maximum no. of items in a tracked itemset.
+#[pyclass]
+#[pyo3(text_signature = "(epsilon, max_length, /)")]
+pub struct LossyCounter {
+    counter: itemsets::stream::LossyCounter,
+    interner: Interner,
+}
+
+#[pymethods]
+impl LossyCounter {
+    #[new]
+    fn new(epsilon: f32, max_length: usize) -> PyResult<Self> {
+        Ok(Self {
+            counter: itemsets::stream::LossyCounter::new(epsilon, max_length)?,
+            interner: Interner::new(),
+        })
+    }
+
+    /// Add a transaction to the stream.
+    #[pyo3(text_signature = "($self, transaction, /)")]
+    fn add(&mut self, raw_transaction: RawTransaction) {
+        let transaction: Vec<usize> = raw_transaction
+            .into_iter()
+            .map(|item| self.interner.intern(item))
+            .collect();
+        self.counter.add(&transaction);
+    }
+
+    /// Add a batch of transactions to the stream.
+    #[pyo3(text_signature = "($self, transactions, /)")]
+    fn extend(&mut self, raw_transactions: Vec<RawTransaction>) {
+        for raw_transaction in raw_transactions {
+            self.add(raw_transaction);
+        }
+    }
+
+    #[getter]
+    fn num_transactions(&self) -> usize {
+        self.counter.num_transactions()
+    }
+
+    /// Current frequent itemsets.
+    ///
+    /// Returns:
+    ///     A tuple of (i) frequent itemsets by size and (ii) a dictionary mapping of item ID to item name.
+    #[pyo3(text_signature = "($self, min_support, /)")]
+    fn frequent_itemsets(
+        &self,
+        min_support: f32,
+    ) -> PyResult<(PyFrequentItemsets, OwnedInventory)> {
+        let itemset_counts = self.counter.frequent_itemsets(min_support)?;
+        Ok((
+            wrapper::convert_itemset_counts(itemset_counts),
+            self.interner.names().clone(),
+        ))
+    }
+
+    /// Association rules over the current frequent itemsets.
+    #[pyo3(text_signature = "($self, min_support, min_confidence, /)")]
+    fn rules(&self, min_support: f32, min_confidence: f32) -> PyResult<Vec<Rule>> {
+        let itemset_counts = self.counter.frequent_itemsets(min_support)?;
+        let rules = rules::search::generate_rules(
+            &min_confidence,
+            &itemset_counts,
+            self.counter.num_transactions(),
+        )?;
+        Ok(wrapper::convert_rules(rules, self.interner.inventory()))
+    }
+}
+
+/// Exact frequent itemsets over the most recent transactions.
+///
+/// Args:
+///     max_length (int): Maximum no. of items in an itemset.
+///     size (Optional[int]): Keep the last `size` transactions.
+///     duration (Optional[float]): Keep the transactions of the last `duration` time units.
+#[pyclass]
+#[pyo3(text_signature = "(max_length, size=None, duration=None)")]
+pub struct SlidingWindow {
+    window: itemsets::window::SlidingWindow,
+    interner: Interner,
+}
+
+#[pymethods]
+impl SlidingWindow {
+    #[new]
+    #[args(size = "None", duration = "None")]
+    fn new(max_length: usize, size: Option<usize>, duration: Option<f64>) -> PyResult<Self> {
+        let size = match (size, duration) {
+            (Some(n), None) => itemsets::window::WindowSize::Count(n),
+            (None, Some(t)) => itemsets::window::WindowSize::Duration(t),
+            _ => {
+                return Err(PyValueError::new_err(
+                    "exactly one of size and duration must be given",
+                ))
+            }
+        };
+        Ok(Self {
+            window: itemsets::window::SlidingWindow::new(size, max_length)?,
+            interner: Interner::new(),
+        })
+    }
+
+    /// Add a transaction to the window. The timestamp defaults to the current
+    /// time in seconds.
+    #[pyo3(text_signature = "($self, transaction, timestamp=None)")]
+    #[args(timestamp = "None")]
+    fn add(&mut self, raw_transaction: RawTransaction, timestamp: Option<f64>) {
+        let transaction: Vec<usize> = raw_transaction
+            .into_iter()
+            .map(|item| self.interner.intern(item))
+            .collect();
+        self.window
+            .add(&transaction, timestamp.unwrap_or_else(wrapper::now));
+    }
+
+    /// Add a batch of transactions sharing a timestamp to the window.
+    #[pyo3(text_signature = "($self, transactions, timestamp=None)")]
+    #[args(timestamp = "None")]
+    fn extend(&mut self, raw_transactions: Vec<RawTransaction>, timestamp: Option<f64>) {
+        let timestamp = timestamp.unwrap_or_else(wrapper::now);
+        for raw_transaction in raw_transactions {
+            self.add(raw_transaction, Some(timestamp));
+        }
+    }
+
+    /// Evict the transactions that are out of the window at the given time.
+    #[pyo3(text_signature = "($self, now=None)")]
+    #[args(now = "None")]
+    fn evict(&mut self, now: Option<f64>) {
+        self.window.evict(now.unwrap_or_else(wrapper::now));
+    }
+
+    /// Frequent itemsets of the transactions in the window.
+    ///
+    /// Returns:
+    ///     A tuple of (i) frequent itemsets by size and (ii) a dictionary mapping of item ID to item name.
+    #[pyo3(text_signature = "($self, min_support, /)")]
+    fn frequent_itemsets(
+        &self,
+        min_support: f32,
+    ) -> PyResult<(PyFrequentItemsets, OwnedInventory)> {
+        let itemset_counts = self.window.frequent_itemsets(min_support)?;
+        Ok((
+            wrapper::convert_itemset_counts(itemset_counts),
+            self.interner.names().clone(),
+        ))
+    }
+
+    /// Association rules over the transactions in the window.
+    #[pyo3(text_signature = "($self, min_support, min_confidence, /)")]
+    fn rules(&self, min_support: f32, min_confidence: f32) -> PyResult<Vec<Rule>> {
+        let itemset_counts = self.window.frequent_itemsets(min_support)?;
+        let rules =
+            rules::search::generate_rules(&min_confidence, &itemset_counts, self.window.len())?;
+        Ok(wrapper::convert_rules(rules, self.interner.inventory()))
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for SlidingWindow {
+    fn __len__(&self) -> usize {
+        self.window.len()
+    }
+}
+
+/// Frequent itemsets and association rules of a set of transactions, along with
+/// the parameters they were mined with. Models can be pickled, and saved to and
+/// loaded from a compact binary format or JSON.
+///
+/// Args:
+///     transactions (List[Set[str]]): A list of list of items.
+///     min_support (float): The minimum support.
+///     min_confidence (float): The minimum confidence.
+///     max_length (int): Maximum no. of items in an association rule.
+#[pyclass(module = "apriori")]
+#[pyo3(text_signature = "(transactions, min_support, min_confidence, max_length, /)")]
+pub struct Model {
+    model: model::Model,
+}
+
+#[pymethods]
+impl Model {
+    #[new]
+    fn new(
+        py: Python,
+        raw_transactions: Vec<RawTransaction>,
+        min_support: f32,
+        min_confidence: f32,
+        max_length: usize,
+    ) -> PyResult<Self> {
+        let model = py.allow_threads(|| {
+            model::Model::fit(raw_transactions, min_support, min_confidence, max_length)
+        })?;
+        Ok(Self { model })
+    }
+
+    #[getter]
+    fn rules(&self) -> Vec<Rule> {
+        let inventory: Inventory = self
+            .model
+            .inventory
+            .iter()
+            .map(|(&item_id, name)| (item_id, name.as_str()))
+            .collect();
+        wrapper::convert_rules(self.model.rules.clone(), inventory)
+    }
+
+    #[getter]
+    fn itemsets(&self) -> PyFrequentItemsets {
+        wrapper::convert_itemset_counts(self.model.itemsets.clone())
+    }
+
+    #[getter]
+    fn id2item(&self) -> OwnedInventory {
+        self.model.inventory.clone()
+    }
+
+    #[getter]
+    fn num_transactions(&self) -> usize {
+        self.model.num_transactions
+    }
+
+    #[getter]
+    fn min_support(&self) -> f32 {
+        self.model.parameters.min_support
+    }
+
+    #[getter]
+    fn min_confidence(&self) -> f32 {
+        self.model.parameters.min_confidence
+    }
+
+    #[getter]
+    fn max_length(&self) -> usize {
+        self.model.parameters.max_length
+    }
+
+    /// Serialize the model to bytes.
+    #[pyo3(text_signature = "($self, /)")]
+    fn to_bytes<'p>(&self, py: Python<'p>) -> &'p PyBytes {
+        PyBytes::new(py, &self.model.to_bytes())
+    }
+
+    /// Deserialize a model from bytes.
+    #[staticmethod]
+    #[pyo3(text_signature = "(data, /)")]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let model = model::Model::from_bytes(data)?;
+        Ok(Self { model })
+    }
+
+    /// Serialize the model to a JSON string.
+    #[pyo3(text_signature = "($self, /)")]
+    fn to_json(&self) -> String {
+        self.model.to_json()
+    }
+
+    /// Deserialize a model from a JSON string.
+    #[staticmethod]
+    #[pyo3(text_signature = "(json, /)")]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let model = model::Model::from_json(json)?;
+        Ok(Self { model })
+    }
+
+    /// Serialize the model as a PMML 4.4 `AssociationModel` document.
+    #[pyo3(text_signature = "($self, /)")]
+    fn to_pmml(&self) -> PyResult<String> {
+        Ok(self.model.to_pmml()?)
+    }
+
+    /// Deserialize a model from a PMML `AssociationModel` document.
+    #[staticmethod]
+    #[pyo3(text_signature = "(pmml, /)")]
+    fn from_pmml(pmml: &str) -> PyResult<Self> {
+        let model = model::Model::from_pmml(pmml)?;
+        Ok(Self { model })
+    }
+
+    /// Save the model to a file in the binary format.
+    #[pyo3(text_signature = "($self, path, /)")]
+    fn save(&self, path: &str) -> PyResult<()> {
+        Ok(self.model.save(path)?)
+    }
+
+    /// Load a model from a file in the binary format.
+    #[staticmethod]
+    #[pyo3(text_signature = "(path, /)")]
+    fn load(path: &str) -> PyResult<Self> {
+        let model = model::Model::load(path)?;
+        Ok(Self { model })
+    }
+
+    fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (PyObject,))> {
+        let from_bytes = py.get_type::<Model>().getattr("from_bytes")?;
+        Ok((from_bytes.into(), (self.to_bytes(py).into(),)))
+    }
+}
+
+/// Recommends items for a (partial) basket from association rules whose
+/// antecedent is contained in the basket. Negative rules are ignored.
+///
+/// Args:
+///     rules (List[Rule]): Association rules, e.g. from `apriori`.
+#[pyclass]
+#[pyo3(text_signature = "(rules, /)")]
+pub struct Recommender {
+    recommender: recommend::Recommender,
+    interner: Interner,
+}
+
+#[pymethods]
+impl Recommender {
+    #[new]
+    fn new(pyrules: Vec<PyRef<Rule>>) -> Self {
+        let mut interner = Interner::new();
+        let rules = pyrules
+            .iter()
+            .map(|pyrule| wrapper::extract_rule(pyrule, &mut interner))
+            .collect();
+        Self {
+            recommender: recommend::Recommender::new(rules),
+            interner,
+        }
+    }
+
+    /// Recommend items for a basket, excluding the items already in it.
+    ///
+    /// Args:
+    ///     basket (Set[str]): Items in the basket.
+    ///     n (int): Maximum no. of items to recommend.
+    ///     method (str): How the scores of the matching rules are aggregated; one of
+    ///         "confidence" (maximum confidence), "lift" (sum of lifts) or
+    ///         "vote" (sum of confidences divided by the no. of matching rules).
+    ///
+    /// Returns:
+    ///     A list of (item, score) tuples, with the highest score first.
+    #[pyo3(text_signature = "($self, basket, n=10, method=\"confidence\")")]
+    #[args(n = "10", method = "\"confidence\"")]
+    fn recommend(
+        &self,
+        basket: RawTransaction,
+        n: usize,
+        method: &str,
+    ) -> PyResult<Vec<(PyItemName, f32)>> {
+        let scoring = match method {
+            "confidence" => recommend::Scoring::MaxConfidence,
+            "lift" => recommend::Scoring::SumLift,
+            "vote" => recommend::Scoring::WeightedVote,
+            _ => return Err(PyValueError::new_err(format!("unknown method: {}", method))),
+        };
+        let basket: Vec<usize> = basket
+            .into_iter()
+            .filter_map(|item| self.interner.get(item))
+            .collect();
+
+        Ok(self
+            .recommender
+            .recommend(&basket, n, scoring)
+            .into_iter()
+            .map(|(item_id, score)| (String::from(self.interner.name(item_id).unwrap()), score))
+            .collect())
+    }
+}
+
+/// Classifier based on class association rules (CBA).
+///
+/// Args:
+///     min_support (float): The minimum support of a rule.
+///     min_confidence (float): The minimum confidence of a rule.
+///     max_length (int): Maximum no. of items in a rule, including the class label.
+#[pyclass]
+#[pyo3(text_signature = "(min_support, min_confidence, max_length, /)")]
+pub struct Classifier {
+    min_support: f32,
+    min_confidence: f32,
+    max_length: usize,
+    classifier: Option<classify::Classifier>,
+    items: Interner,
+    labels: Interner,
+}
+
+impl Classifier {
+    fn fitted(&self) -> PyResult<&classify::Classifier> {
+        self.classifier
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("classifier is not fitted"))
+    }
+
+    fn transactions(&self, raw_transactions: Vec<RawTransaction>) -> Vec<Vec<usize>> {
+        raw_transactions
+            .into_iter()
+            .map(|raw_transaction| {
+                raw_transaction
+                    .into_iter()
+                    .filter_map(|item| self.items.get(item))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Classifier {
+    #[new]
+    fn new(min_support: f32, min_confidence: f32, max_length: usize) -> Self {
+        Self {
+            min_support,
+            min_confidence,
+            max_length,
+            classifier: None,
+            items: Interner::new(),
+            labels: Interner::new(),
+        }
+    }
+
+    /// Mine and select class association rules.
+    ///
+    /// Args:
+    ///     transactions (List[Set[str]]): A list of list of items.
+    ///     labels (List[str]): The class label of each transaction.
+    #[pyo3(text_signature = "($self, transactions, labels, /)")]
+    fn fit(
+        &mut self,
+        py: Python,
+        raw_transactions: Vec<RawTransaction>,
+        labels: Vec<&str>,
+    ) -> PyResult<()> {
+        if raw_transactions.len() != labels.len() {
+            return Err(PyValueError::new_err(
+                "transactions and labels must have the same length",
+            ));
+        }
+        let mut items = Interner::new();
+        let mut label_names = Interner::new();
+        let transactions = wrapper::intern_transactions(raw_transactions, &mut items);
+        let labels: Vec<usize> = labels
+            .into_iter()
+            .map(|label| label_names.intern(label))
+            .collect();
+
+        let (min_support, min_confidence, max_length) =
+            (self.min_support, self.min_confidence, self.max_length);
+        // A failed fit leaves the previous one in place
+        let classifier = py.allow_threads(|| {
+            classify::Classifier::fit(
+                &transactions,
+                &labels,
+                min_support,
+                min_confidence,
+                max_length,
+            )
+        })?;
+        self.items = items;
+        self.labels = label_names;
+        self.classifier = Some(classifier);
+        Ok(())
+    }
+
+    /// Predict the class label of each transaction.
+    #[pyo3(text_signature = "($self, transactions, /)")]
+    fn predict(&self, raw_transactions: Vec<RawTransaction>) -> PyResult<Vec<PyItemName>> {
+        let classifier = self.fitted()?;
+        Ok(self
+            .transactions(raw_transactions)
+            .iter()
+            .map(|transaction| {
+                let label = classifier.predict(transaction);
+                String::from(self.labels.name(label).unwrap())
+            })
+            .collect())
+    }
+
+    /// Fraction of transactions whose predicted class label is the given one.
+    #[pyo3(text_signature = "($self, transactions, labels, /)")]
+    fn score(&self, raw_transactions: Vec<RawTransaction>, labels: Vec<&str>) -> PyResult<f32> {
+        let classifier = self.fitted()?;
+        // Labels not seen during fitting are never predicted
+        let labels: Vec<usize> = labels
+            .into_iter()
+            .map(|label| self.labels.get(label).unwrap_or(usize::MAX))
+            .collect();
+        Ok(classifier.accuracy(&self.transactions(raw_transactions), &labels))
+    }
+
+    /// Selected rules, in order of precedence.
+    #[getter]
+    fn rules(&self) -> PyResult<Vec<Rule>> {
+        let classifier = self.fitted()?;
+        Ok(classifier
+            .rules()
+            .iter()
+            .map(|rule| Rule {
+                antecedent: rule
+                    .get_antecedent()
+                    .iter()
+                    .map(|&item_id| String::from(self.items.name(item_id).unwrap()))
+                    .collect(),
+                consequent: std::iter::once(String::from(
+                    self.labels.name(classifier.class_of(rule)).unwrap(),
+                ))
+                .collect(),
+                support: rule.support,
+                confidence: rule.confidence,
+                lift: rule.lift,
+                negated_antecedent: false,
+                negated_consequent: false,
+            })
+            .collect())
+    }
+
+    #[getter]
+    fn default_class(&self) -> PyResult<PyItemName> {
+        let classifier = self.fitted()?;
+        Ok(String::from(
+            self.labels.name(classifier.default_class()).unwrap(),
+        ))
     }
 }
@@ -0,0 +1,316 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{self, Error},
+    io::pmml,
+    itemsets::count::generate_frequent_itemsets,
+    rules::{rule::Rule, search::generate_rules},
+    types::{FrequentItemsets, OwnedInventory, RawTransaction},
+};
+
+const MAGIC: &[u8; 4] = b"APRM";
+pub const VERSION: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameters {
+    pub min_support: f32,
+    pub min_confidence: f32,
+    pub max_length: usize,
+}
+
+/// Everything produced by a mining run, in a form that can be saved and
+/// loaded again.
+///
+/// The binary format is the magic bytes `APRM`, a little-endian `u16`
+/// version, then the bincode-encoded model (with variable-length integers).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    pub version: u16,
+    pub parameters: Parameters,
+    pub num_transactions: usize,
+    pub inventory: OwnedInventory,
+    #[serde(with = "itemsets_as_list")]
+    pub itemsets: FrequentItemsets,
+    pub rules: Vec<Rule>,
+}
+
+impl Model {
+    pub fn fit(
+        raw_transactions: Vec<RawTransaction>,
+        min_support: f32,
+        min_confidence: f32,
+        max_length: usize,
+    ) -> error::Result<Model> {
+        let N = raw_transactions.len();
+        let (itemsets, inventory) =
+            generate_frequent_itemsets(raw_transactions, min_support, max_length)?;
+        let rules = generate_rules(&min_confidence, &itemsets, N)?;
+
+        Ok(Model {
+            version: VERSION,
+            parameters: Parameters {
+                min_support,
+                min_confidence,
+                max_length,
+            },
+            num_transactions: N,
+            inventory: inventory
+                .into_iter()
+                .map(|(item_id, name)| (item_id, String::from(name)))
+                .collect(),
+            itemsets,
+            rules,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1024);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bincode::options()
+            .serialize_into(&mut bytes, self)
+            .expect("models are always serializable");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> error::Result<Model> {
+        if bytes.len() < 6 || &bytes[..4] != MAGIC {
+            return Err(Error::Format(String::from("missing magic bytes")));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(Error::Version(version));
+        }
+        let model: Model = bincode::options()
+            .deserialize(&bytes[6..])
+            .map_err(|e| Error::Format(e.to_string()))?;
+        // The header and the body must agree
+        if model.version != VERSION {
+            return Err(Error::Version(model.version));
+        }
+        model.validate()?;
+        Ok(model)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("models are always serializable")
+    }
+
+    pub fn from_json(json: &str) -> error::Result<Model> {
+        let model: Model = serde_json::from_str(json).map_err(|e| Error::Format(e.to_string()))?;
+        if model.version != VERSION {
+            return Err(Error::Version(model.version));
+        }
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Serialize the model as a PMML `AssociationModel`; see `io::pmml`.
+    pub fn to_pmml(&self) -> error::Result<String> {
+        let mut bytes = vec![];
+        // Writing to memory only fails if the model cannot be represented
+        pmml::write_pmml(&mut bytes, self).map_err(|e| Error::InvalidModel(e.to_string()))?;
+        Ok(String::from_utf8(bytes).expect("PMML is always UTF-8"))
+    }
+
+    pub fn from_pmml(pmml: &str) -> error::Result<Model> {
+        pmml::read_pmml(pmml)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> error::Result<()> {
+        let mut file = fs::File::create(path)?;
+        file.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> error::Result<Model> {
+        let mut bytes = vec![];
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        Model::from_bytes(&bytes)
+    }
+
+    /// Check that the parameters are in range, that every itemset is sorted,
+    /// has the size it is filed under and only contains known items, and that
+    /// every rule splits a known itemset.
+    pub fn validate(&self) -> error::Result<()> {
+        let invalid = |e: String| Err(Error::InvalidModel(e));
+        let parameters = &self.parameters;
+
+        if !(0.0..=1.0).contains(&parameters.min_support) {
+            return invalid(format!(
+                "min_support {} out of range",
+                parameters.min_support
+            ));
+        }
+        if !(0.0..=1.0).contains(&parameters.min_confidence) {
+            return invalid(format!(
+                "min_confidence {} out of range",
+                parameters.min_confidence
+            ));
+        }
+
+        for (&size, itemset_counts) in &self.itemsets {
+            if size == 0 || size > parameters.max_length {
+                return invalid(format!("itemsets of size {}", size));
+            }
+            for (itemset, &count) in itemset_counts {
+                if itemset.len() != size || itemset.windows(2).any(|w| w[0] >= w[1]) {
+                    return invalid(format!("itemset {:?} filed under size {}", itemset, size));
+                }
+                if let Some(item_id) = itemset.iter().find(|i| !self.inventory.contains_key(i)) {
+                    return invalid(format!("unknown item {}", item_id));
+                }
+                if count as usize > self.num_transactions {
+                    return invalid(format!("itemset {:?} has count {}", itemset, count));
+                }
+            }
+        }
+
+        for rule in &self.rules {
+            if rule.split == 0 || rule.split >= rule.combi.len() {
+                return invalid(format!("rule {:?} has an empty side", rule.combi));
+            }
+            let mut itemset = rule.combi.clone();
+            itemset.sort_unstable();
+            let known = self
+                .itemsets
+                .get(&itemset.len())
+                .is_some_and(|itemset_counts| itemset_counts.contains_key(&itemset));
+            if !known {
+                return invalid(format!("rule over unknown itemset {:?}", itemset));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Itemsets are keyed by `Vec`s, which JSON does not allow as map keys, so
+/// they are stored as a list of `(itemset, count)` per size instead.
+mod itemsets_as_list {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::types::{FrequentItemsets, Itemset, ItemsetLength};
+
+    type Entries = Vec<(ItemsetLength, Vec<(Itemset, u32)>)>;
+
+    pub fn serialize<S: Serializer>(
+        itemsets: &FrequentItemsets,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Entries = itemsets
+            .iter()
+            .map(|(&size, itemset_counts)| {
+                let mut counts: Vec<(Itemset, u32)> = itemset_counts
+                    .iter()
+                    .map(|(itemset, &count)| (itemset.to_owned(), count))
+                    .collect();
+                counts.sort_unstable();
+                (size, counts)
+            })
+            .collect();
+        entries.sort_unstable();
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FrequentItemsets, D::Error> {
+        let entries = Entries::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|(size, counts)| (size, counts.into_iter().collect()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn model() -> Model {
+        let transactions: Vec<RawTransaction> = vec![
+            vec!["bread", "milk", "cheese"],
+            vec!["bread", "milk"],
+            vec!["milk", "cheese"],
+            vec!["bread", "cheese", "milk"],
+        ]
+        .into_iter()
+        .map(|items| items.into_iter().collect::<HashSet<_>>())
+        .collect();
+        Model::fit(transactions, 0.5, 0.5, 3).unwrap()
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let model = model();
+        let loaded = Model::from_bytes(&model.to_bytes()).unwrap();
+
+        assert_eq!(loaded.parameters, model.parameters);
+        assert_eq!(loaded.num_transactions, 4);
+        assert_eq!(loaded.inventory, model.inventory);
+        assert_eq!(loaded.itemsets, model.itemsets);
+        assert_eq!(loaded.rules.len(), model.rules.len());
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let model = model();
+        let loaded = Model::from_json(&model.to_json()).unwrap();
+
+        assert_eq!(loaded.itemsets, model.itemsets);
+        assert_eq!(loaded.rules.len(), model.rules.len());
+    }
+
+    #[test]
+    fn test_rejects_bad_magic() {
+        let mut bytes = model().to_bytes();
+        bytes[0] = b'X';
+        assert!(matches!(Model::from_bytes(&bytes), Err(Error::Format(_))));
+    }
+
+    #[test]
+    fn test_rejects_other_version() {
+        for &version in &[VERSION - 1, VERSION + 1] {
+            let mut bytes = model().to_bytes();
+            bytes[4..6].copy_from_slice(&version.to_le_bytes());
+            assert!(matches!(Model::from_bytes(&bytes), Err(Error::Version(_))));
+        }
+    }
+
+    #[test]
+    fn test_rejects_other_body_version() {
+        let mut model = model();
+        model.version = VERSION + 1;
+        let mut bytes = model.to_bytes();
+        bytes[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        assert!(matches!(Model::from_bytes(&bytes), Err(Error::Version(_))));
+    }
+
+    #[test]
+    fn test_rejects_truncated() {
+        let bytes = model().to_bytes();
+        assert!(matches!(
+            Model::from_bytes(&bytes[..bytes.len() / 2]),
+            Err(Error::Format(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unknown_item() {
+        let mut model = model();
+        model.inventory.remove(&0);
+        assert!(matches!(
+            Model::from_bytes(&model.to_bytes()),
+            Err(Error::InvalidModel(_))
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    error::{Error, Result},
+    itemsets::count::{Counting, ItemOrder, SupportOrder},
+    types::{ItemGroups, ItemId, Itemset},
+};
+use std::{
+    fmt, mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// What a mining run has done so far, reported once per itemset size
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    pub level: usize,
+    pub num_candidates: usize,
+    pub num_frequent: usize,
+    pub elapsed: Duration,
+}
+
+/// A flag that stops a mining run, possibly from another thread. Clones share
+/// the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits on the candidates of a single level. A run stops before a level
+/// that would exceed them, keeping the levels mined so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Budget {
+    pub max_candidates: Option<usize>,
+    /// Estimated bytes taken by the candidates
+    pub max_memory: Option<usize>,
+}
+
+impl Budget {
+    /// Estimated bytes taken by `num_candidates` itemsets of size `level`
+    pub fn memory(level: usize, num_candidates: usize) -> usize {
+        num_candidates.saturating_mul(mem::size_of::<Itemset>() + level * mem::size_of::<ItemId>())
+    }
+
+    pub(crate) fn check(&self, level: usize, num_candidates: usize) -> Option<BudgetExceeded> {
+        let memory = Budget::memory(level, num_candidates);
+        let exceeded = self.max_candidates.is_some_and(|max| num_candidates > max)
+            || self.max_memory.is_some_and(|max| memory > max);
+        if exceeded {
+            Some(BudgetExceeded {
+                level,
+                num_candidates,
+                memory,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// The level at which a run stopped because its candidates would have
+/// exceeded the `Budget`. Itemsets of this size and larger are missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetExceeded {
+    pub level: usize,
+    pub num_candidates: usize,
+    pub memory: usize,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stopped before itemsets of size {}: {} candidates (about {} bytes) exceed the budget",
+            self.level, self.num_candidates, self.memory
+        )
+    }
+}
+
+/// Hooks into a mining run: a callback invoked after each level, a token to
+/// cancel it, and a budget on its candidates; and how candidates are counted
+/// and item IDs assigned or relabeled.
+#[derive(Clone, Default)]
+pub struct MiningOptions<'a> {
+    pub progress: Option<&'a (dyn Fn(&Progress) + Sync)>,
+    pub cancel: Option<CancellationToken>,
+    pub budget: Budget,
+    pub counting: Counting,
+    /// If set, item IDs are reassigned in this order, so that the same
+    /// transactions give the same IDs on every run
+    pub item_order: Option<ItemOrder>,
+    /// If set, frequent items are relabeled in this order of support before
+    /// level 2, and infrequent items dropped. Results keep the original IDs.
+    pub relabel: Option<SupportOrder>,
+    /// If set, candidates never hold two items of the same group, e.g. two
+    /// intervals of a numeric attribute
+    pub item_groups: Option<&'a ItemGroups>,
+}
+
+impl<'a> MiningOptions<'a> {
+    pub fn with_progress(mut self, progress: &'a (dyn Fn(&Progress) + Sync)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn with_counting(mut self, counting: Counting) -> Self {
+        self.counting = counting;
+        self
+    }
+
+    pub fn with_item_order(mut self, item_order: ItemOrder) -> Self {
+        self.item_order = Some(item_order);
+        self
+    }
+
+    pub fn with_relabel(mut self, order: SupportOrder) -> Self {
+        self.relabel = Some(order);
+        self
+    }
+
+    pub fn with_item_groups(mut self, item_groups: &'a ItemGroups) -> Self {
+        self.item_groups = Some(item_groups);
+        self
+    }
+
+    pub(crate) fn check(&self) -> Result<()> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => Err(Error::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn report(
+        &self,
+        start: Instant,
+        level: usize,
+        num_candidates: usize,
+        num_frequent: usize,
+    ) {
+        if let Some(progress) = self.progress {
+            progress(&Progress {
+                level,
+                num_candidates,
+                num_frequent,
+                elapsed: start.elapsed(),
+            });
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+pub type ClassId = usize;
+
+/// How to partition a numeric attribute into intervals.
+pub enum Discretization<'l> {
+    /// Intervals of equal width between the minimum and maximum value.
+    EquiWidth(usize),
+    /// Intervals holding (roughly) the same number of records.
+    EquiDepth(usize),
+    /// Entropy-based partitioning against a class label per record, stopped by
+    /// the MDL criterion (Fayyad & Irani) or when the maximum no. of intervals
+    /// is reached.
+    Supervised {
+        labels: &'l [ClassId],
+        max_bins: usize,
+    },
+}
+
+/// Compute the sorted cut points of `values`. A value `v` falls into bin `i`
+/// where `i` is the number of cut points less than or equal to `v`.
+///
+/// Non-finite values are ignored.
+pub fn cut_points(values: &[f64], discretization: &Discretization) -> Vec<f64> {
+    let mut cuts = match discretization {
+        Discretization::EquiWidth(bins) => equi_width(values, *bins),
+        Discretization::EquiDepth(bins) => equi_depth(values, *bins),
+        Discretization::Supervised { labels, max_bins } => supervised(values, labels, *max_bins),
+    };
+    cuts.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    cuts.dedup();
+    cuts
+}
+
+pub fn bin_of(value: f64, cuts: &[f64]) -> usize {
+    cuts.partition_point(|&cut| cut <= value)
+}
+
+fn finite_sorted(values: &[f64]) -> Vec<f64> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|x| x.is_finite()).collect();
+    sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted
+}
+
+fn equi_width(values: &[f64], bins: usize) -> Vec<f64> {
+    let sorted = finite_sorted(values);
+    if sorted.is_empty() || bins <= 1 {
+        return vec![];
+    }
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let width = (max - min) / bins as f64;
+    if width <= 0.0 {
+        return vec![];
+    }
+    (1..bins).map(|i| min + width * i as f64).collect()
+}
+
+fn equi_depth(values: &[f64], bins: usize) -> Vec<f64> {
+    let sorted = finite_sorted(values);
+    if sorted.is_empty() || bins <= 1 {
+        return vec![];
+    }
+    (1..bins)
+        .map(|i| sorted[i * sorted.len() / bins])
+        .filter(|&cut| cut > sorted[0])
+        .collect()
+}
+
+fn supervised(values: &[f64], labels: &[ClassId], max_bins: usize) -> Vec<f64> {
+    let mut records: Vec<(f64, ClassId)> = values
+        .iter()
+        .copied()
+        .zip(labels.iter().copied())
+        .filter(|(x, _)| x.is_finite())
+        .collect();
+    records.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut cuts = vec![];
+    if max_bins > 1 {
+        split_recursively(&records, max_bins - 1, &mut cuts);
+    }
+    cuts
+}
+
+/// Split `records` (sorted by value) at the boundary that minimises the
+/// class entropy, as long as the MDL criterion accepts it and the cut budget
+/// is not exhausted. Returns the no. of cuts made.
+fn split_recursively(records: &[(f64, ClassId)], budget: usize, cuts: &mut Vec<f64>) -> usize {
+    if budget == 0 || records.len() < 2 {
+        return 0;
+    }
+
+    let n = records.len() as f64;
+    let entropy_all = entropy(records);
+    let mut best: Option<(usize, f64)> = None;
+
+    for i in 1..records.len() {
+        if records[i].0 == records[i - 1].0 {
+            continue;
+        }
+        let (left, right) = records.split_at(i);
+        let weighted =
+            left.len() as f64 / n * entropy(left) + right.len() as f64 / n * entropy(right);
+        if best.is_none() || weighted < best.unwrap().1 {
+            best = Some((i, weighted));
+        }
+    }
+
+    let (i, weighted) = match best {
+        Some(best) => best,
+        None => return 0,
+    };
+    let (left, right) = records.split_at(i);
+
+    // MDL stopping criterion
+    let gain = entropy_all - weighted;
+    let k = num_classes(records) as f64;
+    let k1 = num_classes(left) as f64;
+    let k2 = num_classes(right) as f64;
+    let delta =
+        (3_f64.powf(k) - 2.0).log2() - (k * entropy_all - k1 * entropy(left) - k2 * entropy(right));
+    if gain <= ((n - 1.0).log2() + delta) / n {
+        return 0;
+    }
+
+    cuts.push(records[i].0);
+    let used_left = split_recursively(left, (budget - 1) / 2, cuts);
+    let used_right = split_recursively(right, budget - 1 - used_left, cuts);
+    1 + used_left + used_right
+}
+
+fn class_counts(records: &[(f64, ClassId)]) -> HashMap<ClassId, usize> {
+    let mut counts = HashMap::new();
+    for &(_, label) in records {
+        *counts.entry(label).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn num_classes(records: &[(f64, ClassId)]) -> usize {
+    class_counts(records).len()
+}
+
+fn entropy(records: &[(f64, ClassId)]) -> f64 {
+    let n = records.len() as f64;
+    class_counts(records)
+        .values()
+        .map(|&count| {
+            let p = count as f64 / n;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equi_width() {
+        let values = vec![0.0, 10.0, 2.5, 7.5];
+        let cuts = cut_points(&values, &Discretization::EquiWidth(4));
+        assert_eq!(cuts, vec![2.5, 5.0, 7.5]);
+        assert_eq!(bin_of(0.0, &cuts), 0);
+        assert_eq!(bin_of(2.5, &cuts), 1);
+        assert_eq!(bin_of(10.0, &cuts), 3);
+    }
+
+    #[test]
+    fn test_equi_depth() {
+        let values = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, f64::NAN];
+        let cuts = cut_points(&values, &Discretization::EquiDepth(3));
+        assert_eq!(cuts, vec![3.0, 5.0]);
+    }
+
+    #[test]
+    fn test_equi_depth_repeated_values() {
+        let values = vec![1.0, 1.0, 1.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let cuts = cut_points(&values, &Discretization::EquiDepth(4));
+        assert_eq!(cuts, vec![2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_supervised() {
+        let values = vec![1.0, 2.0, 3.0, 4.0, 11.0, 12.0, 13.0, 14.0];
+        let labels = vec![0, 0, 0, 0, 1, 1, 1, 1];
+        let discretization = Discretization::Supervised {
+            labels: &labels,
+            max_bins: 4,
+        };
+        let cuts = cut_points(&values, &discretization);
+        assert_eq!(cuts, vec![11.0]);
+    }
+
+    #[test]
+    fn test_supervised_pure_class() {
+        let values = vec![1.0, 2.0, 3.0, 4.0];
+        let labels = vec![0, 0, 0, 0];
+        let discretization = Discretization::Supervised {
+            labels: &labels,
+            max_bins: 4,
+        };
+        assert!(cut_points(&values, &discretization).is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+pub mod discretize;
+
+use crate::{
+    error::{check_fraction, Error, Result},
+    interner::Interner,
+    itemsets::count::generate_frequent_itemsets_from_transactions_with_options,
+    progress::MiningOptions,
+    rules::{rule::Rule, search::generate_rules},
+    types::{FrequentItemsets, ItemGroups, OwnedInventory, RawTransaction, Transaction},
+};
+use discretize::{bin_of, cut_points, Discretization};
+
+/// A numeric column, with one value per transaction. Missing values are NaN.
+pub struct NumericAttribute<'l> {
+    pub name: &'l str,
+    pub values: Vec<f64>,
+}
+
+/// Mine association rules over categorical items and interval items of
+/// numeric attributes, e.g. `age∈[25,34] ∧ bread -> milk`.
+///
+/// Each numeric attribute is discretized into base intervals. Adjacent base
+/// intervals are merged into wider interval items as long as the support of
+/// the merged interval does not exceed `max_support`. Itemsets (and hence
+/// rules) never contain more than one interval of the same attribute.
+pub fn generate_quantitative_rules(
+    raw_transactions: Vec<RawTransaction>,
+    attributes: &[NumericAttribute],
+    discretization: &Discretization,
+    min_support: f32,
+    min_conf: f32,
+    max_length: usize,
+    max_support: f32,
+) -> Result<(Vec<Rule>, FrequentItemsets, OwnedInventory)> {
+    check_fraction("max_support", max_support)?;
+    let N = raw_transactions.len();
+    if let Some(attribute) = attributes
+        .iter()
+        .find(|attribute| attribute.values.len() != N)
+    {
+        return Err(Error::InvalidParameter(format!(
+            "{} has {} values for {} transactions",
+            attribute.name,
+            attribute.values.len(),
+            N
+        )));
+    }
+
+    // Interval items of an attribute form a group, so that candidates never
+    // join two of them
+    let mut interner = Interner::new();
+    let mut item_groups = ItemGroups::new();
+    let mut transactions: Vec<Transaction> = raw_transactions
+        .into_iter()
+        .map(|raw_transaction| {
+            raw_transaction
+                .into_iter()
+                .map(|item| interner.intern(item))
+                .collect()
+        })
+        .collect();
+    for (attribute_id, attribute) in attributes.iter().enumerate() {
+        let items = generate_interval_items(attribute, discretization, max_support);
+        for (transaction, items) in transactions.iter_mut().zip(items) {
+            for item in items {
+                let item_id = interner.intern(&item);
+                item_groups.insert(item_id, attribute_id);
+                transaction.push(item_id);
+            }
+        }
+    }
+    for transaction in &mut transactions {
+        transaction.sort_unstable();
+        transaction.dedup();
+    }
+
+    let options = MiningOptions::default().with_item_groups(&item_groups);
+    let (itemset_counts, _) = generate_frequent_itemsets_from_transactions_with_options(
+        transactions,
+        min_support,
+        max_length,
+        &options,
+    )?;
+
+    let rules = generate_rules(&min_conf, &itemset_counts, N)?;
+
+    Ok((rules, itemset_counts, interner.into_inventory()))
+}
+
+/// Interval items of every record, including the merged adjacent intervals
+/// that contain the record's base interval.
+fn generate_interval_items(
+    attribute: &NumericAttribute,
+    discretization: &Discretization,
+    max_support: f32,
+) -> Vec<Vec<String>> {
+    let cuts = cut_points(&attribute.values, discretization);
+    let finite = attribute.values.iter().copied().filter(|x| x.is_finite());
+    let min = finite.clone().fold(f64::INFINITY, f64::min);
+    let max = finite.fold(f64::NEG_INFINITY, f64::max);
+    let num_bins = cuts.len() + 1;
+
+    let bins: Vec<Option<usize>> = attribute
+        .values
+        .iter()
+        .map(|&x| {
+            if x.is_finite() {
+                Some(bin_of(x, &cuts))
+            } else {
+                None
+            }
+        })
+        .collect();
+    let mut bin_counts = vec![0_usize; num_bins];
+    for bin in bins.iter().flatten() {
+        bin_counts[*bin] += 1;
+    }
+
+    // Base intervals are always kept, merged intervals only up to max_support
+    let max_support_count = (max_support * attribute.values.len() as f32).floor() as usize;
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for first in 0..num_bins {
+        let mut count = bin_counts[first];
+        ranges.push((first, first));
+        for (last, bin_count) in bin_counts.iter().enumerate().skip(first + 1) {
+            count += bin_count;
+            if count > max_support_count {
+                break;
+            }
+            ranges.push((first, last));
+        }
+    }
+
+    let names: Vec<String> = ranges
+        .iter()
+        .map(|&(first, last)| {
+            let lower = if first == 0 { min } else { cuts[first - 1] };
+            let (upper, bracket) = if last == num_bins - 1 {
+                (max, ']')
+            } else {
+                (cuts[last], ')')
+            };
+            format!("{}∈[{},{}{}", attribute.name, lower, upper, bracket)
+        })
+        .collect();
+
+    bins.iter()
+        .map(|bin| match bin {
+            Some(bin) => ranges
+                .iter()
+                .zip(names.iter())
+                .filter(|((first, last), _)| first <= bin && bin <= last)
+                .map(|(_, name)| name.clone())
+                .collect(),
+            None => vec![],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    macro_rules! hashset {
+        ($($x:expr),*) => {
+            {
+                let mut set: HashSet<_> = HashSet::new();
+                $(set.insert($x);)*
+                set
+            }
+        };
+    }
+
+    #[test]
+    fn test_interval_items() {
+        let attribute = NumericAttribute {
+            name: "age",
+            values: vec![20.0, 30.0, 40.0, f64::NAN],
+        };
+        let items = generate_interval_items(&attribute, &Discretization::EquiWidth(2), 1.0);
+
+        assert_eq!(
+            items,
+            vec![
+                vec!["age∈[20,30)".to_string(), "age∈[20,40]".to_string()],
+                vec!["age∈[20,40]".to_string(), "age∈[30,40]".to_string()],
+                vec!["age∈[20,40]".to_string(), "age∈[30,40]".to_string()],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interval_items_max_support() {
+        let attribute = NumericAttribute {
+            name: "age",
+            values: vec![20.0, 30.0, 40.0, 50.0],
+        };
+        let items = generate_interval_items(&attribute, &Discretization::EquiWidth(2), 0.25);
+
+        assert_eq!(
+            items,
+            vec![
+                vec!["age∈[20,35)".to_string()],
+                vec!["age∈[20,35)".to_string()],
+                vec!["age∈[35,50]".to_string()],
+                vec!["age∈[35,50]".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_quantitative_rules() {
+        let transactions = vec![
+            hashset!["bread", "milk"],
+            hashset!["bread", "milk"],
+            hashset!["bread"],
+            hashset!["bread"],
+        ];
+        let attributes = vec![NumericAttribute {
+            name: "age",
+            values: vec![20.0, 22.0, 60.0, 62.0],
+        }];
+
+        let (rules, itemset_counts, inventory) = generate_quantitative_rules(
+            transactions,
+            &attributes,
+            &Discretization::EquiWidth(2),
+            0.5,
+            1.0,
+            3,
+            0.5,
+        )
+        .unwrap();
+
+        let names: Vec<(Vec<&str>, Vec<&str>)> = rules
+            .iter()
+            .map(|rule| {
+                let mut antecedent: Vec<&str> = rule
+                    .get_antecedent()
+                    .iter()
+                    .map(|item_id| inventory[item_id].as_str())
+                    .collect();
+                antecedent.sort_unstable();
+                let consequent = rule
+                    .get_consequent()
+                    .iter()
+                    .map(|item_id| inventory[item_id].as_str())
+                    .collect();
+                (antecedent, consequent)
+            })
+            .collect();
+
+        assert!(names.contains(&(vec!["milk"], vec!["age∈[20,41)"])));
+        assert!(names.contains(&(vec!["age∈[20,41)", "bread"], vec!["milk"])));
+        assert!(itemset_counts[&2].keys().all(|itemset| itemset
+            .iter()
+            .filter(|i| inventory[i].starts_with("age"))
+            .count()
+            <= 1));
+    }
+
+    #[test]
+    fn test_one_interval_per_attribute() {
+        let transactions = vec![hashset!["bread"]; 4];
+        let attributes = vec![NumericAttribute {
+            name: "age",
+            values: vec![20.0, 30.0, 40.0, 50.0],
+        }];
+
+        // Merged intervals overlap the base ones, in every transaction
+        let (_, itemset_counts, inventory) = generate_quantitative_rules(
+            transactions,
+            &attributes,
+            &Discretization::EquiWidth(2),
+            0.25,
+            0.5,
+            4,
+            1.0,
+        )
+        .unwrap();
+
+        assert!(itemset_counts[&1].len() > 2);
+        for itemset in itemset_counts.values().flat_map(|counts| counts.keys()) {
+            let num_intervals = itemset
+                .iter()
+                .filter(|i| inventory[i].starts_with("age"))
+                .count();
+            assert!(num_intervals <= 1, "{:?}", itemset);
+        }
+        assert!(itemset_counts[&2]
+            .keys()
+            .any(|itemset| itemset.iter().any(|i| inventory[i].starts_with("age"))));
+    }
+}
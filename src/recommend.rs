@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{rules::rule::Rule, types::ItemId};
+
+/// How the rules that match a basket are aggregated into a score per item.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scoring {
+    /// Highest confidence of the rules recommending the item
+    MaxConfidence,
+    /// Sum of the lifts of the rules recommending the item
+    SumLift,
+    /// Sum of the confidences of the rules recommending the item, divided by
+    /// the no. of rules matching the basket
+    WeightedVote,
+}
+
+/// Recommends items for a (partial) basket from association rules whose
+/// antecedent is contained in the basket.
+pub struct Recommender {
+    rules: Vec<Rule>,
+    /// Rules by the first item of their antecedent
+    index: HashMap<ItemId, Vec<usize>>,
+}
+
+impl Recommender {
+    /// Build a recommender from mined rules. Negative rules are ignored.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let rules: Vec<Rule> = rules
+            .into_iter()
+            .filter(|rule| !rule.is_negative())
+            .collect();
+        let mut index: HashMap<ItemId, Vec<usize>> = HashMap::new();
+        for (i, rule) in rules.iter().enumerate() {
+            if let Some(&first) = rule.get_antecedent().iter().min() {
+                index.entry(first).or_default().push(i);
+            }
+        }
+        Self { rules, index }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rules whose antecedent is contained in the basket
+    pub fn matching_rules<'a>(
+        &'a self,
+        basket: &'a HashSet<ItemId>,
+    ) -> impl Iterator<Item = &'a Rule> {
+        basket
+            .iter()
+            .filter_map(move |item| self.index.get(item))
+            .flatten()
+            .map(move |&i| &self.rules[i])
+            .filter(move |rule| {
+                rule.get_antecedent()
+                    .iter()
+                    .all(|item| basket.contains(item))
+            })
+    }
+
+    /// The top `n` items by score, excluding the items already in the basket
+    /// and those whose score is not finite, e.g. from a NaN lift. Ties are
+    /// broken by item ID.
+    pub fn recommend(&self, basket: &[ItemId], n: usize, scoring: Scoring) -> Vec<(ItemId, f32)> {
+        let basket: HashSet<ItemId> = basket.iter().copied().collect();
+        let mut scores: HashMap<ItemId, f32> = HashMap::new();
+        let mut num_matching_rules = 0;
+
+        for rule in self.matching_rules(&basket) {
+            num_matching_rules += 1;
+            for &item in rule.get_consequent() {
+                if basket.contains(&item) {
+                    continue;
+                }
+                let score = scores.entry(item).or_insert(0.0);
+                match scoring {
+                    Scoring::MaxConfidence => *score = score.max(rule.confidence),
+                    Scoring::SumLift => *score += rule.lift,
+                    Scoring::WeightedVote => *score += rule.confidence,
+                }
+            }
+        }
+
+        if scoring == Scoring::WeightedVote {
+            for score in scores.values_mut() {
+                *score /= num_matching_rules as f32;
+            }
+        }
+
+        let mut recommendations: Vec<(ItemId, f32)> = scores
+            .into_iter()
+            .filter(|(_, score)| score.is_finite())
+            .collect();
+        recommendations.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        recommendations.truncate(n);
+        recommendations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(antecedent: &[ItemId], consequent: &[ItemId], confidence: f32, lift: f32) -> Rule {
+        let mut rule = Rule::from_split(antecedent, consequent, false, false);
+        rule.confidence = confidence;
+        rule.lift = lift;
+        rule
+    }
+
+    fn recommender() -> Recommender {
+        Recommender::new(vec![
+            rule(&[1], &[2], 0.9, 1.5),
+            rule(&[1], &[3], 0.6, 2.0),
+            rule(&[1, 4], &[3], 0.8, 3.0),
+            rule(&[5], &[6], 1.0, 4.0),
+            rule(&[1], &[4], 0.7, 1.0),
+        ])
+    }
+
+    #[test]
+    fn test_max_confidence() {
+        let recommendations = recommender().recommend(&[1, 4], 10, Scoring::MaxConfidence);
+        assert_eq!(recommendations, vec![(2, 0.9), (3, 0.8)]);
+    }
+
+    #[test]
+    fn test_sum_lift() {
+        let recommendations = recommender().recommend(&[1, 4], 10, Scoring::SumLift);
+        assert_eq!(recommendations, vec![(3, 5.0), (2, 1.5)]);
+    }
+
+    #[test]
+    fn test_weighted_vote() {
+        let recommendations = recommender().recommend(&[1], 1, Scoring::WeightedVote);
+        assert_eq!(recommendations.len(), 1);
+        assert_eq!(recommendations[0].0, 2);
+        assert!((recommendations[0].1 - 0.9 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ignores_negative_rules() {
+        let recommender = Recommender::new(vec![Rule::from_split(&[1], &[2], false, true)]);
+        assert!(recommender.is_empty());
+        assert!(recommender
+            .recommend(&[1], 10, Scoring::MaxConfidence)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_nan_score() {
+        let recommender = Recommender::new(vec![
+            rule(&[1], &[2], 0.5, f32::NAN),
+            rule(&[1], &[3], 0.5, 2.0),
+            rule(&[1], &[4], 0.5, f32::INFINITY),
+        ]);
+        let recommendations = recommender.recommend(&[1], 10, Scoring::SumLift);
+        assert_eq!(recommendations, vec![(3, 2.0)]);
+    }
+}
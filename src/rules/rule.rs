@@ -1,25 +1,62 @@
 #![allow(non_snake_case)]
 
-use crate::types::{FrequentItemsets, ItemId};
+use crate::{
+    error::{Error, Result},
+    types::{FrequentItemsets, ItemId},
+};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Rule {
     pub split: usize,
     pub combi: Vec<ItemId>,
+    pub support: f32,
     pub confidence: f32,
     pub lift: f32,
+    pub negated_antecedent: bool,
+    pub negated_consequent: bool,
 }
 
 impl Rule {
     pub fn from_pattern(pattern: &[ItemId]) -> Vec<Rule> {
         let mother = Rule {
             split: pattern.len(),
-            combi: pattern.iter().copied().collect(),
+            combi: pattern.to_vec(),
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
-        mother.create_children(&[], None).unwrap()
+        // A single item has no split
+        mother.create_children(&[], None).unwrap_or_default()
+    }
+
+    /// Create a rule with the given antecedent and consequent, either of which
+    /// may be negated. Both parts are expected to be sorted.
+    pub fn from_split(
+        antecedent: &[ItemId],
+        consequent: &[ItemId],
+        negated_antecedent: bool,
+        negated_consequent: bool,
+    ) -> Rule {
+        let mut combi = Vec::with_capacity(antecedent.len() + consequent.len());
+        combi.extend_from_slice(antecedent);
+        combi.extend_from_slice(consequent);
+        Rule {
+            split: antecedent.len(),
+            combi,
+            support: 0.0,
+            confidence: 0.0,
+            lift: 0.0,
+            negated_antecedent,
+            negated_consequent,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negated_antecedent || self.negated_consequent
     }
     pub fn create_children(
         &self,
@@ -47,8 +84,11 @@ impl Rule {
             let rule = Self {
                 split: new_split,
                 combi,
+                support: 0.0,
                 confidence: 0.0,
                 lift: 0.0,
+                negated_antecedent: false,
+                negated_consequent: false,
             };
 
             if rule.is_going_to_be_created(to_create) {
@@ -95,21 +135,117 @@ impl Rule {
         let conseq = self.get_consequent();
         parent.get_consequent().iter().all(|x| conseq.contains(x))
     }
-    pub fn compute_confidence(&mut self, counter: &FrequentItemsets, combi: &[ItemId], N: f32) {
-        let antecedent_support_count =
-            counter[&self.get_antecedent().len()][self.get_antecedent()] as f32;
-        let consequent_support_count =
-            counter[&self.get_consequent().len()][self.get_consequent()] as f32;
-        let union_support_count = counter[&self.combi.len()][combi] as f32;
+    pub fn compute_confidence(
+        &mut self,
+        counter: &FrequentItemsets,
+        combi: &[ItemId],
+        N: f32,
+    ) -> Result<()> {
+        let antecedent_support_count = support_count(counter, self.get_antecedent())?;
+        let consequent_support_count = support_count(counter, self.get_consequent())?;
+        let union_support_count = support_count(counter, combi)?;
+        self.support = union_support_count / N;
         self.confidence = union_support_count / antecedent_support_count;
-        self.lift = union_support_count / (antecedent_support_count * consequent_support_count) * N
+        self.lift = union_support_count / (antecedent_support_count * consequent_support_count) * N;
+        Ok(())
+    }
+
+    /// Compute confidence and lift of a rule with either a negated antecedent
+    /// or a negated consequent, using only the support counts of the positive
+    /// itemsets:
+    ///
+    /// * `A -> ¬B`: `(sup(A) - sup(AB)) / sup(A)`
+    /// * `¬A -> B`: `(sup(B) - sup(AB)) / (N - sup(A))`
+    ///
+    /// Rules that negate both parts or neither are rejected.
+    pub fn compute_negative_confidence(
+        &mut self,
+        counter: &FrequentItemsets,
+        combi: &[ItemId],
+        N: f32,
+    ) -> Result<()> {
+        let union_support_count = support_count(counter, combi)?;
+        self.compute_negative_confidence_with_union(counter, union_support_count, N)
+    }
+
+    /// Like `compute_negative_confidence`, given the support count of `AB`,
+    /// e.g. of an infrequent itemset missing from `counter`
+    pub fn compute_negative_confidence_with_union(
+        &mut self,
+        counter: &FrequentItemsets,
+        union_support_count: f32,
+        N: f32,
+    ) -> Result<()> {
+        let antecedent_support_count = support_count(counter, self.get_antecedent())?;
+        let consequent_support_count = support_count(counter, self.get_consequent())?;
+
+        let (rule_support_count, antecedent_support_count, consequent_support_count) =
+            match (self.negated_antecedent, self.negated_consequent) {
+                (false, true) => (
+                    antecedent_support_count - union_support_count,
+                    antecedent_support_count,
+                    N - consequent_support_count,
+                ),
+                (true, false) => (
+                    consequent_support_count - union_support_count,
+                    N - antecedent_support_count,
+                    consequent_support_count,
+                ),
+                _ => {
+                    return Err(Error::InvalidParameter(String::from(
+                        "negative rule must negate either its antecedent or its consequent",
+                    )))
+                }
+            };
+
+        self.support = rule_support_count / N;
+        if antecedent_support_count <= 0.0 || consequent_support_count <= 0.0 {
+            self.confidence = 0.0;
+            self.lift = 0.0;
+            return Ok(());
+        }
+        self.confidence = rule_support_count / antecedent_support_count;
+        self.lift = self.confidence / consequent_support_count * N;
+        Ok(())
     }
 }
 
+/// Sort rules by descending confidence, support and lift, then by antecedent,
+/// consequent and negation, so that ties are ordered the same on every run.
+pub fn sort_rules(rules: &mut [Rule]) {
+    rules.sort_unstable_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then(b.support.total_cmp(&a.support))
+            .then(b.lift.total_cmp(&a.lift))
+            .then_with(|| a.get_antecedent().cmp(b.get_antecedent()))
+            .then_with(|| a.get_consequent().cmp(b.get_consequent()))
+            .then(a.negated_antecedent.cmp(&b.negated_antecedent))
+            .then(a.negated_consequent.cmp(&b.negated_consequent))
+    });
+}
+
+/// Exact support count of an itemset
+pub(crate) fn itemset_count(counter: &FrequentItemsets, itemset: &[ItemId]) -> Result<u32> {
+    counter
+        .get(&itemset.len())
+        .and_then(|itemset_counts| itemset_counts.get(itemset))
+        .copied()
+        .ok_or_else(|| Error::MissingCount(itemset.to_vec()))
+}
+
+/// Support count of an itemset, as a float for computing metrics
+pub(crate) fn support_count(counter: &FrequentItemsets, itemset: &[ItemId]) -> Result<f32> {
+    itemset_count(counter, itemset).map(|count| count as f32)
+}
+
 impl PartialEq for Rule {
     fn eq(&self, other: &Rule) -> bool {
         // assumes same pattern
-        self.split == other.split && self.combi[self.split..] == other.combi[self.split..]
+        self.split == other.split
+            && self.negated_antecedent == other.negated_antecedent
+            && self.negated_consequent == other.negated_consequent
+            && self.combi[self.split..] == other.combi[self.split..]
     }
 }
 
@@ -123,8 +259,10 @@ mod test {
         fn fmt(&self, f: &mut Formatter<'_>) -> Result {
             write!(
                 f,
-                "{:?} => {:?}",
+                "{}{:?} => {}{:?}",
+                if self.negated_antecedent { "¬" } else { "" },
                 &self.combi[..self.split],
+                if self.negated_consequent { "¬" } else { "" },
                 &self.combi[self.split..]
             )
         }
@@ -135,14 +273,20 @@ mod test {
         let rule1 = Rule {
             split: 2,
             combi: vec![1, 2, 3, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         let rule2 = Rule {
             split: 2,
             combi: vec![1, 2, 3, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         assert!(rule1 == rule2);
     }
@@ -151,14 +295,20 @@ mod test {
         let rule1 = Rule {
             split: 2,
             combi: vec![1, 2, 3, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         let rule2 = Rule {
             split: 2,
             combi: vec![9, 10, 3, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         assert!(rule1 == rule2);
     }
@@ -167,14 +317,20 @@ mod test {
         let rule1 = Rule {
             split: 2,
             combi: vec![1, 2, 3, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         let rule2 = Rule {
             split: 2,
             combi: vec![9, 10, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         assert!(rule1 != rule2);
     }
@@ -183,14 +339,20 @@ mod test {
         let rules = VecDeque::from(vec![Rule {
             split: 3,
             combi: vec![1, 3, 4, 2],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         }]);
         let rule = Rule {
             split: 2,
             combi: vec![3, 5, 1, 2],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         assert!(!rules.contains(&rule));
     }
@@ -200,8 +362,11 @@ mod test {
         let rule = Rule {
             split: 4,
             combi: vec![1, 2, 3, 4, 5],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         let mut children = rule.create_children(&[], None).unwrap();
         let child = children.pop().unwrap();
@@ -226,8 +391,11 @@ mod test {
                 1, 2, 3, 4, // ante
                 5, // conseq
             ],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         let child = Rule {
             split: 3,
@@ -235,11 +403,106 @@ mod test {
                 1, 2, 3, // ante
                 4, 5, //conseq
             ],
+            support: 0.0,
             confidence: 0.0,
             lift: 0.0,
+            negated_antecedent: false,
+            negated_consequent: false,
         };
         assert!(child.is_child_of(&parent));
     }
+    #[test]
+    fn test_negative_confidence() {
+        let counter: FrequentItemsets = maplit::hashmap! {
+            1 => maplit::hashmap! {
+                vec![1] => 6,
+                vec![2] => 4,
+            },
+            2 => maplit::hashmap! {
+                vec![1, 2] => 3,
+            },
+        };
+
+        let mut rule = Rule::from_split(&[1], &[2], false, true);
+        rule.compute_negative_confidence(&counter, &[1, 2], 10.0)
+            .unwrap();
+        assert!((rule.support - 0.3).abs() < 1e-6);
+        assert!((rule.confidence - 0.5).abs() < 1e-6);
+        assert!((rule.lift - 0.5 / 0.6).abs() < 1e-6);
+
+        let mut rule = Rule::from_split(&[1], &[2], true, false);
+        rule.compute_negative_confidence(&counter, &[1, 2], 10.0)
+            .unwrap();
+        assert!((rule.confidence - 0.25).abs() < 1e-6);
+        assert!((rule.lift - 0.25 / 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_negative_confidence_invalid() {
+        let counter: FrequentItemsets = maplit::hashmap! {
+            1 => maplit::hashmap! { vec![1] => 6, vec![2] => 4 },
+            2 => maplit::hashmap! { vec![1, 2] => 3 },
+        };
+
+        for &negated in &[false, true] {
+            let mut rule = Rule::from_split(&[1], &[2], negated, negated);
+            assert!(matches!(
+                rule.compute_negative_confidence(&counter, &[1, 2], 10.0),
+                Err(Error::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_missing_count() {
+        let counter: FrequentItemsets = maplit::hashmap! {
+            1 => maplit::hashmap! { vec![1] => 6 },
+            2 => maplit::hashmap! { vec![1, 2] => 3 },
+        };
+
+        let mut rule = Rule::from_split(&[1], &[2], false, false);
+        assert!(matches!(
+            rule.compute_confidence(&counter, &[1, 2], 10.0),
+            Err(Error::MissingCount(itemset)) if itemset == vec![2]
+        ));
+        assert!(Rule::from_pattern(&[1]).is_empty());
+    }
+
+    #[test]
+    fn test_sort_rules() {
+        let rule = |antecedent: &[ItemId], consequent: &[ItemId], confidence, negated| {
+            let mut rule = Rule::from_split(antecedent, consequent, false, negated);
+            rule.support = 0.5;
+            rule.confidence = confidence;
+            rule.lift = 1.0;
+            rule
+        };
+        let mut rules = vec![
+            rule(&[2], &[1], 0.5, false),
+            rule(&[1], &[3], 0.5, true),
+            rule(&[1], &[3], 0.5, false),
+            rule(&[3], &[1], 0.8, false),
+            rule(&[1], &[2], 0.5, false),
+        ];
+
+        sort_rules(&mut rules);
+
+        let order: Vec<_> = rules
+            .iter()
+            .map(|rule| (rule.to_string(), rule.confidence))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (String::from("[3] => [1]"), 0.8),
+                (String::from("[1] => [2]"), 0.5),
+                (String::from("[1] => [3]"), 0.5),
+                (String::from("[1] => ¬[3]"), 0.5),
+                (String::from("[2] => [1]"), 0.5),
+            ]
+        );
+    }
+
     #[test]
     fn test_create_children() {
         let pattern = vec![1, 2, 3, 4, 5];
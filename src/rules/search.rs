@@ -1,38 +1,47 @@
 #![allow(non_snake_case)]
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 
 use crate::{
+    error::{check_fraction, Result},
+    progress::MiningOptions,
     rules::rule::Rule,
     types::{FrequentItemsets, ItemId, Itemset},
 };
 
-/// Generate rules based on frequent itemsets
-pub fn generate_rules(min_conf: &f32, counter: &FrequentItemsets, N: usize) -> Vec<Rule> {
+/// Generate rules based on frequent itemsets. Every subset of a frequent
+/// itemset must have a count.
+pub fn generate_rules(min_conf: &f32, counter: &FrequentItemsets, N: usize) -> Result<Vec<Rule>> {
+    generate_rules_with_options(min_conf, counter, N, &MiningOptions::default())
+}
+
+/// Generate rules based on frequent itemsets, stopping early if cancelled
+pub fn generate_rules_with_options(
+    min_conf: &f32,
+    counter: &FrequentItemsets,
+    N: usize,
+    options: &MiningOptions,
+) -> Result<Vec<Rule>> {
+    check_fraction("min_confidence", *min_conf)?;
     let N = N as f32;
-    counter
-        .iter()
-        .filter_map(|(&itemset_size, itemset_counts)| {
-            if itemset_size > 1 {
-                Some(itemset_counts)
-            } else {
-                None
-            }
-        })
-        .flat_map(|itemset_counts| {
-            itemset_counts
-                .iter()
-                .flat_map(|(combi, _)| {
-                    let combi: Itemset = combi.iter().copied().collect();
-                    bfs(&combi, min_conf, counter, N)
-                })
-                .collect::<Vec<Rule>>()
-        })
-        .collect()
+    let mut rules = vec![];
+    for (_, itemset_counts) in counter.iter().filter(|(&itemset_size, _)| itemset_size > 1) {
+        for combi in itemset_counts.keys() {
+            options.check()?;
+            let combi: Itemset = combi.to_vec();
+            rules.extend(bfs(&combi, min_conf, counter, N)?);
+        }
+    }
+    Ok(rules)
 }
 
 /// Given a combination, find a list of rules that can be generated from it
-pub fn bfs(combi: &[ItemId], &min_conf: &f32, counter: &FrequentItemsets, N: f32) -> Vec<Rule> {
+pub fn bfs(
+    combi: &[ItemId],
+    &min_conf: &f32,
+    counter: &FrequentItemsets,
+    N: f32,
+) -> Result<Vec<Rule>> {
     let mut queue: VecDeque<Rule> = VecDeque::new();
     let mut blacklist = vec![];
     let mut final_rules = vec![];
@@ -45,7 +54,7 @@ pub fn bfs(combi: &[ItemId], &min_conf: &f32, counter: &FrequentItemsets, N: f32
             continue;
         }
 
-        rule.compute_confidence(counter, combi, N);
+        rule.compute_confidence(counter, combi, N)?;
 
         if rule.confidence >= min_conf {
             if let Some(new_rules) = rule.create_children(&blacklist, Some(&queue)) {
@@ -57,7 +66,141 @@ pub fn bfs(combi: &[ItemId], &min_conf: &f32, counter: &FrequentItemsets, N: f32
         }
     }
 
-    final_rules
+    Ok(final_rules)
+}
+
+/// Generate rules whose consequent is a single item of `consequents` and whose
+/// antecedent holds none of them, e.g. class association rules
+pub fn generate_rules_with_consequents(
+    min_conf: &f32,
+    counter: &FrequentItemsets,
+    N: usize,
+    consequents: &HashSet<ItemId>,
+) -> Result<Vec<Rule>> {
+    check_fraction("min_confidence", *min_conf)?;
+    let N = N as f32;
+    let mut rules = vec![];
+    for (_, itemset_counts) in counter.iter().filter(|(&itemset_size, _)| itemset_size > 1) {
+        for combi in itemset_counts.keys() {
+            let mut targets = combi.iter().filter(|item| consequents.contains(item));
+            let consequent = match (targets.next(), targets.next()) {
+                (Some(&consequent), None) => consequent,
+                _ => continue,
+            };
+            let antecedent: Itemset = combi
+                .iter()
+                .copied()
+                .filter(|&item| item != consequent)
+                .collect();
+            let mut rule = Rule::from_split(&antecedent, &[consequent], false, false);
+            rule.compute_confidence(counter, combi, N)?;
+            if rule.confidence >= *min_conf {
+                rules.push(rule);
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Generate negative rules (`A -> ¬B` and `¬A -> B`) based on frequent
+/// itemsets, and on the itemsets counted but found infrequent, e.g. `A -> ¬B`
+/// when `A` and `B` are frequent but rarely occur together. Every subset of a
+/// frequent itemset must have a count; splits of an infrequent itemset are
+/// only considered if both parts are frequent.
+pub fn generate_negative_rules(
+    min_conf: &f32,
+    counter: &FrequentItemsets,
+    infrequent: &FrequentItemsets,
+    N: usize,
+) -> Result<Vec<Rule>> {
+    generate_negative_rules_with_options(
+        min_conf,
+        counter,
+        infrequent,
+        N,
+        &MiningOptions::default(),
+    )
+}
+
+/// Generate negative rules based on frequent and infrequent itemsets,
+/// stopping early if cancelled
+pub fn generate_negative_rules_with_options(
+    min_conf: &f32,
+    counter: &FrequentItemsets,
+    infrequent: &FrequentItemsets,
+    N: usize,
+    options: &MiningOptions,
+) -> Result<Vec<Rule>> {
+    check_fraction("min_confidence", *min_conf)?;
+    let N = N as f32;
+    let mut rules = vec![];
+    for (_, itemset_counts) in counter.iter().filter(|(&itemset_size, _)| itemset_size > 1) {
+        for (combi, &count) in itemset_counts {
+            options.check()?;
+            rules.extend(negative_splits(combi, count, min_conf, counter, N, false)?);
+        }
+    }
+    for (_, itemset_counts) in infrequent
+        .iter()
+        .filter(|(&itemset_size, _)| itemset_size > 1)
+    {
+        for (combi, &count) in itemset_counts {
+            options.check()?;
+            rules.extend(negative_splits(combi, count, min_conf, counter, N, true)?);
+        }
+    }
+    Ok(rules)
+}
+
+/// Given a combination with `union_support_count`, find the negative rules
+/// over every split of it into an antecedent and a consequent, skipping the
+/// splits with a part missing from `counter` if `skip_infrequent_parts`
+fn negative_splits(
+    combi: &[ItemId],
+    union_support_count: u32,
+    &min_conf: &f32,
+    counter: &FrequentItemsets,
+    N: f32,
+    skip_infrequent_parts: bool,
+) -> Result<Vec<Rule>> {
+    let is_frequent = |itemset: &[ItemId]| {
+        counter
+            .get(&itemset.len())
+            .is_some_and(|itemset_counts| itemset_counts.contains_key(itemset))
+    };
+    let mut final_rules = vec![];
+    let mut antecedent = Vec::with_capacity(combi.len());
+    let mut consequent = Vec::with_capacity(combi.len());
+
+    for mask in 1..(1_usize << combi.len()) - 1 {
+        antecedent.clear();
+        consequent.clear();
+        for (i, &item) in combi.iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                antecedent.push(item);
+            } else {
+                consequent.push(item);
+            }
+        }
+        if skip_infrequent_parts && !(is_frequent(&antecedent) && is_frequent(&consequent)) {
+            continue;
+        }
+
+        for &(negated_antecedent, negated_consequent) in &[(false, true), (true, false)] {
+            let mut rule = Rule::from_split(
+                &antecedent,
+                &consequent,
+                negated_antecedent,
+                negated_consequent,
+            );
+            rule.compute_negative_confidence_with_union(counter, union_support_count as f32, N)?;
+            if rule.confidence >= min_conf {
+                final_rules.push(rule);
+            }
+        }
+    }
+
+    Ok(final_rules)
 }
 
 #[cfg(test)]
@@ -65,7 +208,7 @@ mod tests {
     use super::*;
     use maplit::hashmap;
 
-    use crate::types::FrequentItemsets;
+    use crate::{error::Error, progress::CancellationToken, types::FrequentItemsets};
 
     #[test]
     fn test_1() {
@@ -96,7 +239,7 @@ mod tests {
         };
         let min_conf = 0.8;
 
-        let assoc_rules = generate_rules(&min_conf, &counter, 1);
+        let assoc_rules = generate_rules(&min_conf, &counter, 1).unwrap();
 
         for r in &assoc_rules {
             println!("{}", r);
@@ -132,10 +275,171 @@ mod tests {
         };
         let min_conf = 0.8;
 
-        let assoc_rules = generate_rules(&min_conf, &counter, 1);
+        let assoc_rules = generate_rules(&min_conf, &counter, 1).unwrap();
 
         for r in &assoc_rules {
             println!("{}", r);
         }
     }
+
+    #[test]
+    fn test_cancelled() {
+        let counter: FrequentItemsets = hashmap! {
+            1 => hashmap! { vec![1] => 2, vec![2] => 2 },
+            2 => hashmap! { vec![1, 2] => 2 },
+        };
+        let token = CancellationToken::new();
+        let options = MiningOptions::default().with_cancel(token.clone());
+        assert_eq!(
+            generate_rules_with_options(&0.5, &counter, 2, &options)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        token.cancel();
+        assert!(matches!(
+            generate_rules_with_options(&0.5, &counter, 2, &options).unwrap_err(),
+            Error::Cancelled
+        ));
+    }
+
+    #[test]
+    fn test_negative_rules() {
+        let counter: FrequentItemsets = hashmap! {
+            1 => hashmap! {
+                vec![1] => 8,
+                vec![2] => 3,
+            },
+            2 => hashmap! {
+                vec![1, 2] => 1,
+            },
+        };
+        let min_conf = 0.8;
+
+        let assoc_rules =
+            generate_negative_rules(&min_conf, &counter, &FrequentItemsets::new(), 10).unwrap();
+
+        // {1} -> ¬{2}: (8 - 1) / 8
+        // ¬{2} -> {1}: (8 - 1) / (10 - 3)
+        // ¬{1} -> {2}: (3 - 1) / (10 - 8)
+        assert_eq!(assoc_rules.len(), 3);
+        assert!(assoc_rules.iter().all(|rule| rule.is_negative()));
+        assert!(assoc_rules.contains(&Rule::from_split(&[1], &[2], false, true)));
+        assert!(assoc_rules.contains(&Rule::from_split(&[2], &[1], true, false)));
+        assert!(assoc_rules.contains(&Rule::from_split(&[1], &[2], true, false)));
+    }
+
+    #[test]
+    fn test_negative_rules_infrequent_union() {
+        let counter: FrequentItemsets = hashmap! {
+            1 => hashmap! {
+                vec![1] => 8,
+                vec![2] => 3,
+                vec![3] => 4,
+            },
+            2 => hashmap! {
+                vec![1, 3] => 4,
+            },
+        };
+        // {1, 2} never occurs and {2, 3} once; {1, 2, 3} has an infrequent part
+        let infrequent: FrequentItemsets = hashmap! {
+            2 => hashmap! {
+                vec![1, 2] => 0,
+                vec![2, 3] => 1,
+            },
+            3 => hashmap! {
+                vec![1, 2, 3] => 0,
+            },
+        };
+
+        let assoc_rules = generate_negative_rules(&0.8, &counter, &infrequent, 10).unwrap();
+
+        // {1} -> ¬{2}: 8 / 8
+        // {2} -> ¬{1}: 3 / 3
+        // ¬{1} -> {2}: 3 / (10 - 8)
+        // {2} -> ¬{3}: (3 - 1) / 3 is too low
+        // ¬{2} -> {1}: 8 / (10 - 3)
+        let find = |antecedent: &[ItemId], consequent: &[ItemId], negated_antecedent| {
+            assoc_rules.iter().find(|rule| {
+                rule.get_antecedent() == antecedent
+                    && rule.get_consequent() == consequent
+                    && rule.negated_antecedent == negated_antecedent
+                    && rule.negated_consequent != negated_antecedent
+            })
+        };
+        assert!(find(&[1], &[2], true).is_some());
+        assert!(find(&[2], &[1], false).is_some());
+        assert!(find(&[2], &[1], true).is_some());
+        assert!(find(&[2], &[3], false).is_none());
+        let never = find(&[1], &[2], false).unwrap();
+        assert!((never.confidence - 1.0).abs() < 1e-6);
+        assert!((never.support - 0.8).abs() < 1e-6);
+        // Only splits of {1, 2, 3} into frequent parts, {1, 3} and {2}
+        assert!(assoc_rules
+            .iter()
+            .filter(|r| r.combi.len() == 3)
+            .all(|r| r.get_antecedent() == [2] || r.get_consequent() == [2]));
+    }
+
+    #[test]
+    fn test_negative_rules_cancelled() {
+        let counter: FrequentItemsets = hashmap! {
+            1 => hashmap! { vec![1] => 8, vec![2] => 3 },
+            2 => hashmap! { vec![1, 2] => 1 },
+        };
+        let infrequent = FrequentItemsets::new();
+        let token = CancellationToken::new();
+        let options = MiningOptions::default().with_cancel(token.clone());
+        assert_eq!(
+            generate_negative_rules_with_options(&0.8, &counter, &infrequent, 10, &options)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        token.cancel();
+        assert!(matches!(
+            generate_negative_rules_with_options(&0.8, &counter, &infrequent, 10, &options)
+                .unwrap_err(),
+            Error::Cancelled
+        ));
+    }
+
+    #[test]
+    fn test_rules_with_consequents() {
+        let counter: FrequentItemsets = hashmap! {
+            1 => hashmap! {
+                vec![1] => 4,
+                vec![2] => 4,
+                vec![8] => 3,
+                vec![9] => 2,
+            },
+            2 => hashmap! {
+                vec![1, 2] => 3,
+                vec![1, 8] => 3,
+                vec![2, 8] => 2,
+                vec![2, 9] => 2,
+                vec![8, 9] => 1,
+            },
+            3 => hashmap! {
+                vec![1, 2, 8] => 2,
+            },
+        };
+        let consequents: HashSet<ItemId> = vec![8, 9].into_iter().collect();
+
+        let rules = generate_rules_with_consequents(&0.5, &counter, 5, &consequents).unwrap();
+
+        // {1, 2} is not a rule, nor is {8, 9}, and {2} -> {8} has 0.5
+        assert_eq!(rules.len(), 4);
+        assert!(rules.contains(&Rule::from_split(&[1], &[8], false, false)));
+        assert!(rules.contains(&Rule::from_split(&[2], &[8], false, false)));
+        assert!(rules.contains(&Rule::from_split(&[2], &[9], false, false)));
+        assert!(rules.contains(&Rule::from_split(&[1, 2], &[8], false, false)));
+        let rule = rules
+            .iter()
+            .find(|rule| rule.get_antecedent() == [1])
+            .unwrap();
+        assert!((rule.confidence - 0.75).abs() < 1e-6);
+    }
 }
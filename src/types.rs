@@ -9,6 +9,7 @@ pub type Itemset = Vec<ItemId>;
 
 pub type ReverseLookup<'l> = HashMap<ItemName<'l>, ItemId>;
 pub type Inventory<'l> = HashMap<ItemId, ItemName<'l>>;
+pub type OwnedInventory = HashMap<ItemId, PyItemName>;
 
 pub type RawTransaction<'l> = HashSet<ItemName<'l>>;
 pub type RawTransactionId = HashSet<ItemId>;
@@ -17,6 +18,9 @@ pub type Transaction = Vec<ItemId>;
 pub type ItemCounts = HashMap<ItemId, u32>;
 pub type ItemsetCounts = HashMap<Itemset, u32>;
 
+/// Group of each item, of which an itemset may hold at most one item
+pub type ItemGroups = HashMap<ItemId, usize>;
+
 pub type ItemsetLength = usize;
 pub type FrequentItemsets = HashMap<ItemsetLength, ItemsetCounts>;
 pub type PyFrequentItemsets = Py<PyDict>;
@@ -1,9 +1,24 @@
-use crate::types::{FrequentItemsets, Inventory};
+use crate::error;
+use crate::interner::Interner;
+use crate::io::columnar::{
+    self, Dictionary, ItemListColumn, ItemsetColumns, Primitive, RuleColumns,
+};
+use crate::progress::{BudgetExceeded, CancellationToken, MiningOptions, Progress};
+use crate::types::{
+    FrequentItemsets, Inventory, ItemId, Itemset, ItemsetCounts, ItemsetLength, OwnedInventory,
+    RawTransaction, Transaction,
+};
 use crate::rules;
 use crate::Rule;
+use pyo3::buffer::{Element, PyBuffer, ReadOnlyCell};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use pyo3::types::{IntoPyDict, PyDict, PyFrozenSet};
-use std::cmp::Ordering::Equal;
+use pyo3::PyNativeType;
+use pyo3::types::{IntoPyDict, PyBytes, PyDict, PyFrozenSet};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 macro_rules! pyfrozenset {
     ($py:expr,$x:expr) => {{
@@ -12,9 +27,20 @@ macro_rules! pyfrozenset {
     }};
 }
 
+/// Itemsets by ascending size, and of each size in order of item IDs, so that
+/// the Python dicts built from them are ordered
+fn sorted_itemset_counts(
+    itemset_counts: FrequentItemsets,
+) -> BTreeMap<ItemsetLength, BTreeMap<Itemset, u32>> {
+    itemset_counts
+        .into_iter()
+        .map(|(size, itemset_counts)| (size, itemset_counts.into_iter().collect()))
+        .collect()
+}
+
 pub fn convert_itemset_counts_id(itemset_counts: FrequentItemsets) -> Py<PyDict> {
     Python::with_gil(|py| {
-        itemset_counts
+        sorted_itemset_counts(itemset_counts)
             .into_iter()
             .map(|(size, itemset_counts)| {
                 let py_itemset_counts: Py<PyDict> = itemset_counts
@@ -32,7 +58,7 @@ pub fn convert_itemset_counts_id(itemset_counts: FrequentItemsets) -> Py<PyDict>
 
 pub fn convert_itemset_counts(itemset_counts: FrequentItemsets) -> Py<PyDict> {
     Python::with_gil(|py| {
-        itemset_counts
+        sorted_itemset_counts(itemset_counts)
             .into_iter()
             .map(|(size, itemset_counts)| {
                 let py_itemset_counts: Py<PyDict> = itemset_counts
@@ -48,8 +74,79 @@ pub fn convert_itemset_counts(itemset_counts: FrequentItemsets) -> Py<PyDict> {
     })
 }
 
-pub fn convert_rules(rules: Vec<rules::rule::Rule>, inventory: Inventory) -> Vec<Rule> {
-    let mut pyrules: Vec<Rule> = rules
+/// Inverse of `convert_itemset_counts`
+pub fn extract_itemset_counts(py_itemset_counts: &PyDict) -> PyResult<FrequentItemsets> {
+    py_itemset_counts
+        .iter()
+        .map(|(size, py_counts)| {
+            let itemset_counts = py_counts
+                .downcast::<PyDict>()?
+                .iter()
+                .map(|(itemset, count)| {
+                    let mut itemset = itemset
+                        .downcast::<PyFrozenSet>()?
+                        .iter()
+                        .map(|item_id| item_id.extract())
+                        .collect::<PyResult<Vec<usize>>>()?;
+                    itemset.sort_unstable();
+                    Ok((itemset, count.extract()?))
+                })
+                .collect::<PyResult<ItemsetCounts>>()?;
+            Ok((size.extract()?, itemset_counts))
+        })
+        .collect()
+}
+
+/// Map raw transactions to sorted transactions of item IDs, assigning new IDs
+/// to unseen items
+pub fn intern_transactions(
+    raw_transactions: Vec<RawTransaction>,
+    interner: &mut Interner,
+) -> Vec<Transaction> {
+    raw_transactions
+        .into_iter()
+        .map(|raw_transaction| {
+            let mut transaction: Transaction = raw_transaction
+                .into_iter()
+                .map(|item| interner.intern(item))
+                .collect();
+            transaction.sort_unstable();
+            transaction
+        })
+        .collect()
+}
+
+/// Inverse of `convert_rules`, assigning item IDs with `interner`
+pub fn extract_rule(pyrule: &Rule, interner: &mut Interner) -> rules::rule::Rule {
+    let mut antecedent: Vec<usize> = pyrule
+        .antecedent
+        .iter()
+        .map(|item| interner.intern(item))
+        .collect();
+    antecedent.sort_unstable();
+    let mut consequent: Vec<usize> = pyrule
+        .consequent
+        .iter()
+        .map(|item| interner.intern(item))
+        .collect();
+    consequent.sort_unstable();
+
+    let mut rule = rules::rule::Rule::from_split(
+        &antecedent,
+        &consequent,
+        pyrule.negated_antecedent,
+        pyrule.negated_consequent,
+    );
+    rule.support = pyrule.support;
+    rule.confidence = pyrule.confidence;
+    rule.lift = pyrule.lift;
+    rule
+}
+
+/// Rules in the order of `sort_rules`
+pub fn convert_rules(mut rules: Vec<rules::rule::Rule>, inventory: Inventory) -> Vec<Rule> {
+    rules::rule::sort_rules(&mut rules);
+    rules
         .into_iter()
         .map(|x| Rule {
             antecedent: x
@@ -62,10 +159,584 @@ pub fn convert_rules(rules: Vec<rules::rule::Rule>, inventory: Inventory) -> Vec
                 .iter()
                 .map(|item_id| String::from(inventory[item_id]))
                 .collect(),
+            support: x.support,
             confidence: x.confidence,
             lift: x.lift,
+            negated_antecedent: x.negated_antecedent,
+            negated_consequent: x.negated_consequent,
+        })
+        .collect()
+}
+
+/// The progress callback and cancellation token of a mining run started from
+/// Python. If the callback raises, the run is cancelled and the exception is
+/// raised in its place.
+pub struct MiningHooks {
+    progress: Option<PyObject>,
+    pub token: CancellationToken,
+    error: Mutex<Option<PyObject>>,
+}
+
+impl MiningHooks {
+    pub fn new(progress: Option<PyObject>, token: Option<CancellationToken>) -> Self {
+        MiningHooks {
+            progress,
+            token: token.unwrap_or_default(),
+            error: Mutex::new(None),
+        }
+    }
+
+    /// Call the callback with (level, num_candidates, num_frequent, elapsed
+    /// seconds), taking the GIL
+    pub fn report(&self, progress: &Progress) {
+        if let Some(callback) = &self.progress {
+            Python::with_gil(|py| {
+                let args = (
+                    progress.level,
+                    progress.num_candidates,
+                    progress.num_frequent,
+                    progress.elapsed.as_secs_f64(),
+                );
+                if let Err(e) = callback.call1(py, args) {
+                    self.error.lock().unwrap().get_or_insert(e.into_py(py));
+                    self.token.cancel();
+                }
+            });
+        }
+    }
+
+    pub fn options<'a>(&self, report: &'a (dyn Fn(&Progress) + Sync)) -> MiningOptions<'a> {
+        let options = MiningOptions::default().with_cancel(self.token.clone());
+        match self.progress {
+            Some(_) => options.with_progress(report),
+            None => options,
+        }
+    }
+
+    /// The exception the callback raised, if any
+    pub fn take_error(&self) -> Option<PyObject> {
+        self.error.lock().unwrap().take()
+    }
+
+    pub fn result<T>(&self, py: Python, result: error::Result<T>) -> PyResult<T> {
+        result.map_err(|e| match (e, self.take_error()) {
+            (error::Error::Cancelled, Some(error)) => PyErr::from_instance(error.as_ref(py)),
+            (e, _) => e.into(),
+        })
+    }
+}
+
+/// Issue a `RuntimeWarning` if mining stopped before a level because of its
+/// budget, so that the partial results are not mistaken for complete ones
+pub fn warn_exceeded(py: Python, exceeded: Option<&BudgetExceeded>) -> PyResult<()> {
+    match exceeded {
+        Some(exceeded) => {
+            let category = py.import("builtins")?.getattr("RuntimeWarning")?;
+            PyErr::warn(py, category, &exceeded.to_string(), 1)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Seconds since the Unix epoch, the default timestamp of Python transactions
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+/// Read a one-hot matrix into transactions of column indices, with the column
+/// names as item names. The matrix is a 2-D bool or uint8 array (e.g. numpy),
+/// a pandas DataFrame of such columns, or a scipy sparse matrix, whose nonzero
+/// entries are the items. With `long_columns`, the matrix is instead a long
+/// DataFrame with a row per item of a transaction, in the given (transaction,
+/// item) columns. Array and column buffers are read in place.
+pub fn extract_matrix(
+    matrix: &PyAny,
+    columns: Option<Vec<&PyAny>>,
+    long_columns: Option<(&PyAny, &PyAny)>,
+) -> PyResult<(Vec<Transaction>, OwnedInventory)> {
+    let py = matrix.py();
+    if let Some((transaction_column, item_column)) = long_columns {
+        if columns.is_some() {
+            return Err(PyValueError::new_err(
+                "columns cannot be given with long_columns, items are named by their values",
+            ));
+        }
+        return long_transactions(py, matrix, transaction_column, item_column);
+    }
+    let columns = match columns {
+        Some(columns) => Some(columns),
+        None if matrix.hasattr("columns")? => Some(matrix.getattr("columns")?.extract()?),
+        None => None,
+    };
+
+    let (transactions, num_columns) = if matrix.hasattr("tocsr")? {
+        let csr = matrix.call_method0("tocsr")?;
+        let (num_rows, num_columns): (usize, usize) = csr.getattr("shape")?.extract()?;
+        // scipy indexes with int32, or int64 for large matrices
+        let index_size: usize = csr
+            .getattr("indptr")?
+            .getattr("dtype")?
+            .getattr("itemsize")?
+            .extract()?;
+        let transactions = match index_size {
+            4 => csr_transactions::<i32>(py, csr, num_columns)?,
+            _ => csr_transactions::<i64>(py, csr, num_columns)?,
+        };
+        if transactions.len() != num_rows {
+            return Err(PyValueError::new_err("indptr does not match the shape"));
+        }
+        (transactions, num_columns)
+    } else if matrix.hasattr("columns")? {
+        frame_transactions(py, matrix)?
+    } else {
+        dense_transactions(py, matrix)?
+    };
+
+    let inventory: OwnedInventory = match columns {
+        Some(columns) => {
+            if columns.len() != num_columns {
+                return Err(PyValueError::new_err(format!(
+                    "expected {} column names, got {}",
+                    num_columns,
+                    columns.len()
+                )));
+            }
+            columns
+                .into_iter()
+                .enumerate()
+                .map(|(item_id, name)| Ok((item_id, name.str()?.to_string())))
+                .collect::<PyResult<_>>()?
+        }
+        None => (0..num_columns)
+            .map(|item_id| (item_id, item_id.to_string()))
+            .collect(),
+    };
+    Ok((transactions, inventory))
+}
+
+/// A buffer of bytes of a bool or uint8 array
+fn byte_buffer(array: &PyAny) -> PyResult<PyBuffer<u8>> {
+    match PyBuffer::<u8>::get(array) {
+        Ok(buffer) => Ok(buffer),
+        // numpy bool arrays have their own buffer format, but the same layout
+        Err(e) => match array.getattr("dtype") {
+            Ok(dtype) if dtype.getattr("itemsize")?.extract::<usize>()? == 1 => {
+                PyBuffer::<u8>::get(array.call_method1("view", ("uint8",))?)
+            }
+            _ => Err(e),
+        },
+    }
+}
+
+fn dense_transactions(py: Python, array: &PyAny) -> PyResult<(Vec<Transaction>, usize)> {
+    let buffer = byte_buffer(array)?;
+    if buffer.dimensions() != 2 {
+        return Err(PyValueError::new_err("expected a 2-D matrix"));
+    }
+    let (num_rows, num_columns) = (buffer.shape()[0], buffer.shape()[1]);
+    let (row_stride, column_stride) = (buffer.strides()[0], buffer.strides()[1]);
+    let data = buffer.buf_ptr() as *const u8;
+
+    let transactions = (0..num_rows)
+        .map(|i| {
+            (0..num_columns)
+                .filter(|&j| {
+                    // Safety: the offset is within the buffer given its shape
+                    // and strides, and the GIL is held
+                    let offset = i as isize * row_stride + j as isize * column_stride;
+                    unsafe { *data.offset(offset) != 0 }
+                })
+                .collect()
         })
         .collect();
-    pyrules.sort_by(|a, b| (-a.confidence).partial_cmp(&-b.confidence).unwrap_or(Equal));
-    pyrules
+    buffer.release(py);
+    Ok((transactions, num_columns))
+}
+
+/// Transactions of a DataFrame of bool or uint8 columns, each read in place
+/// from the numpy array backing it
+fn frame_transactions(py: Python, frame: &PyAny) -> PyResult<(Vec<Transaction>, usize)> {
+    let num_rows = frame.len()?;
+    let num_columns = frame.getattr("columns")?.len()?;
+    let iloc = frame.getattr("iloc")?;
+    let all_rows = py
+        .import("builtins")?
+        .getattr("slice")?
+        .call1((py.None(),))?;
+
+    let mut transactions: Vec<Transaction> = vec![Vec::new(); num_rows];
+    for j in 0..num_columns {
+        let values = iloc.get_item((all_rows, j))?.getattr("values")?;
+        let buffer = byte_buffer(values)
+            .map_err(|_| PyValueError::new_err("expected bool or uint8 columns"))?;
+        if buffer.dimensions() != 1 || buffer.shape()[0] != num_rows {
+            return Err(PyValueError::new_err("expected bool or uint8 columns"));
+        }
+        let stride = buffer.strides()[0];
+        let data = buffer.buf_ptr() as *const u8;
+        // Columns are visited in order, so transactions stay sorted
+        for (i, transaction) in transactions.iter_mut().enumerate() {
+            // Safety: the offset is within the buffer given its shape and
+            // stride, and the GIL is held
+            if unsafe { *data.offset(i as isize * stride) } != 0 {
+                transaction.push(j);
+            }
+        }
+        buffer.release(py);
+    }
+    Ok((transactions, num_columns))
+}
+
+/// Transactions of a long DataFrame, whose transaction and item columns are
+/// factorized by pandas into codes read in place. Items are named by their
+/// values, and transactions ordered by first appearance. Rows with a missing
+/// value are skipped.
+fn long_transactions(
+    py: Python,
+    frame: &PyAny,
+    transaction_column: &PyAny,
+    item_column: &PyAny,
+) -> PyResult<(Vec<Transaction>, OwnedInventory)> {
+    let pd = py.import("pandas")?;
+    let factorize = |column: &PyAny| -> PyResult<(&PyAny, &PyAny)> {
+        pd.call_method1("factorize", (frame.get_item(column)?,))?
+            .extract()
+    };
+    let (transaction_codes, transaction_ids) = factorize(transaction_column)?;
+    let (item_codes, items) = factorize(item_column)?;
+
+    let transaction_buffer = PyBuffer::<i64>::get(transaction_codes)?;
+    let item_buffer = PyBuffer::<i64>::get(item_codes)?;
+    let transactions = match (transaction_buffer.as_slice(py), item_buffer.as_slice(py)) {
+        (Some(transaction_codes), Some(item_codes)) => {
+            long_rows(transaction_codes, item_codes, transaction_ids.len()?)
+                .ok_or_else(|| PyValueError::new_err("invalid long DataFrame"))
+        }
+        _ => Err(PyValueError::new_err("expected C-contiguous codes")),
+    };
+    transaction_buffer.release(py);
+    item_buffer.release(py);
+
+    let inventory = items
+        .iter()?
+        .enumerate()
+        .map(|(item_id, name)| Ok((item_id, name?.str()?.to_string())))
+        .collect::<PyResult<_>>()?;
+    Ok((transactions?, inventory))
+}
+
+/// Transactions of `num_transactions` given the transaction and item code of
+/// each row, skipping the negative codes of missing values, or `None` if the
+/// codes are inconsistent
+fn long_rows<C: Entry<Value = i64>>(
+    transaction_codes: &[C],
+    item_codes: &[C],
+    num_transactions: usize,
+) -> Option<Vec<Transaction>> {
+    if transaction_codes.len() != item_codes.len() {
+        return None;
+    }
+    let mut transactions: Vec<Transaction> = vec![Vec::new(); num_transactions];
+    for (transaction_code, item_code) in transaction_codes.iter().zip(item_codes) {
+        let (transaction_code, item_code) = (transaction_code.value(), item_code.value());
+        if transaction_code < 0 || item_code < 0 {
+            continue;
+        }
+        transactions
+            .get_mut(transaction_code as usize)?
+            .push(item_code as ItemId);
+    }
+    for transaction in &mut transactions {
+        transaction.sort_unstable();
+        transaction.dedup();
+    }
+    Some(transactions)
+}
+
+/// A value of an array, read in place from a buffer
+trait Entry {
+    type Value: Copy;
+
+    fn value(&self) -> Self::Value;
+}
+
+impl<T: Element + Copy> Entry for ReadOnlyCell<T> {
+    type Value = T;
+
+    fn value(&self) -> T {
+        self.get()
+    }
+}
+
+/// Transactions of a CSR matrix, whose arrays are read in place
+fn csr_transactions<T>(py: Python, csr: &PyAny, num_columns: usize) -> PyResult<Vec<Transaction>>
+where
+    T: Element + Copy + TryInto<usize>,
+{
+    let indptr_buffer = PyBuffer::<T>::get(csr.getattr("indptr")?)?;
+    let indices_buffer = PyBuffer::<T>::get(csr.getattr("indices")?)?;
+    let transactions = match (indptr_buffer.as_slice(py), indices_buffer.as_slice(py)) {
+        (Some(indptr), Some(indices)) => {
+            csr_data_transactions(py, csr.getattr("data")?, indptr, indices, num_columns)
+        }
+        _ => Err(not_contiguous()),
+    };
+    indptr_buffer.release(py);
+    indices_buffer.release(py);
+    transactions
+}
+
+/// Transactions of a CSR matrix given its `indptr` and `indices`, with `data`
+/// read in place as whichever numeric type it holds
+fn csr_data_transactions<T>(
+    py: Python,
+    data: &PyAny,
+    indptr: &[ReadOnlyCell<T>],
+    indices: &[ReadOnlyCell<T>],
+    num_columns: usize,
+) -> PyResult<Vec<Transaction>>
+where
+    T: Element + Copy + TryInto<usize>,
+{
+    // numpy bool arrays have their own buffer format, but the layout of uint8
+    let data = if data.getattr("dtype")?.getattr("kind")?.extract::<&str>()? == "b" {
+        data.call_method1("view", ("uint8",))?
+    } else {
+        data
+    };
+    macro_rules! read_data {
+        ($($t:ty),*) => {
+            $(
+                if let Ok(buffer) = PyBuffer::<$t>::get(data) {
+                    let transactions = buffer
+                        .as_slice(py)
+                        .ok_or_else(not_contiguous)
+                        .map(|data| sparse_rows(indptr, indices, data, num_columns));
+                    buffer.release(py);
+                    return transactions?
+                        .ok_or_else(|| PyValueError::new_err("invalid sparse matrix"));
+                }
+            )*
+        };
+    }
+    read_data!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+    Err(PyValueError::new_err(
+        "expected a sparse matrix of bool or numeric data",
+    ))
+}
+
+fn not_contiguous() -> PyErr {
+    PyValueError::new_err("expected C-contiguous sparse matrix arrays")
+}
+
+/// Rows of a CSR matrix as transactions of the columns of their nonzero
+/// entries, or `None` if the arrays are inconsistent. Stored entries may be
+/// explicit zeros, which are not items.
+fn sparse_rows<I, D>(
+    indptr: &[I],
+    indices: &[I],
+    data: &[D],
+    num_columns: usize,
+) -> Option<Vec<Transaction>>
+where
+    I: Entry,
+    I::Value: TryInto<usize>,
+    D: Entry,
+    D::Value: Default + PartialEq,
+{
+    if data.len() < indices.len() {
+        return None;
+    }
+    let as_usize = |x: &I| x.value().try_into().ok();
+    let zero = D::Value::default();
+    indptr
+        .windows(2)
+        .map(|window| {
+            let (start, end) = (as_usize(&window[0])?, as_usize(&window[1])?);
+            let mut transaction = Vec::with_capacity(end.saturating_sub(start));
+            for (index, value) in indices.get(start..end)?.iter().zip(&data[start..end]) {
+                let item_id = as_usize(index).filter(|&item_id| item_id < num_columns)?;
+                if value.value() != zero {
+                    transaction.push(item_id);
+                }
+            }
+            transaction.sort_unstable();
+            transaction.dedup();
+            Some(transaction)
+        })
+        .collect()
+}
+
+/// Frequent itemsets as a pyarrow RecordBatch with columns `itemset`
+/// (list<string>), `support_count` and `support`
+pub fn itemsets_to_arrow(
+    py: Python,
+    itemsets: &FrequentItemsets,
+    inventory: &Inventory,
+    N: usize,
+) -> PyResult<PyObject> {
+    let pa = py.import("pyarrow")?;
+    let dictionary = Dictionary::new(inventory);
+    let names = pa.call_method1("array", (dictionary.names.clone(), pa.call_method0("string")?))?;
+    let columns = ItemsetColumns::new(itemsets, &dictionary, N);
+
+    let arrays = vec![
+        arrow_list(pa, names, &columns.itemset)?,
+        arrow_primitive(pa, "uint32", &columns.support_count)?,
+        arrow_primitive(pa, "float32", &columns.support)?,
+    ];
+    arrow_record_batch(pa, arrays, &["itemset", "support_count", "support"])
+}
+
+/// Rules as a pyarrow RecordBatch with columns `antecedent` and `consequent`
+/// (list<string>), `support_count`, `support`, `confidence`, `lift`,
+/// `antecedent_support`, `consequent_support`, `leverage`, `conviction`,
+/// `negated_antecedent` and `negated_consequent`. Rules are sorted as in
+/// `convert_rules`.
+pub fn rules_to_arrow(
+    py: Python,
+    mut rules: Vec<rules::rule::Rule>,
+    itemsets: &FrequentItemsets,
+    inventory: &Inventory,
+    N: usize,
+) -> PyResult<PyObject> {
+    rules::rule::sort_rules(&mut rules);
+    let pa = py.import("pyarrow")?;
+    let dictionary = Dictionary::new(inventory);
+    let names = pa.call_method1("array", (dictionary.names.clone(), pa.call_method0("string")?))?;
+    let columns = RuleColumns::new(&rules, itemsets, &dictionary, N)?;
+
+    let arrays = vec![
+        arrow_list(pa, names, &columns.antecedent)?,
+        arrow_list(pa, names, &columns.consequent)?,
+        arrow_primitive(pa, "uint32", &columns.support_count)?,
+        arrow_primitive(pa, "float32", &columns.support)?,
+        arrow_primitive(pa, "float32", &columns.confidence)?,
+        arrow_primitive(pa, "float32", &columns.lift)?,
+        arrow_primitive(pa, "float32", &columns.antecedent_support)?,
+        arrow_primitive(pa, "float32", &columns.consequent_support)?,
+        arrow_primitive(pa, "float32", &columns.leverage)?,
+        arrow_primitive(pa, "float32", &columns.conviction)?,
+        arrow_bool(pa, &columns.negated_antecedent)?,
+        arrow_bool(pa, &columns.negated_consequent)?,
+    ];
+    arrow_record_batch(
+        pa,
+        arrays,
+        &[
+            "antecedent",
+            "consequent",
+            "support_count",
+            "support",
+            "confidence",
+            "lift",
+            "antecedent_support",
+            "consequent_support",
+            "leverage",
+            "conviction",
+            "negated_antecedent",
+            "negated_consequent",
+        ],
+    )
+}
+
+/// A pyarrow buffer holding a copy of `values`
+fn arrow_buffer<'p, T: Primitive>(pa: &'p PyModule, values: &[T]) -> PyResult<&'p PyAny> {
+    let bytes = columnar::to_bytes(values);
+    pa.call_method1("py_buffer", (PyBytes::new(pa.py(), &bytes),))
+}
+
+fn arrow_primitive<'p, T: Primitive>(
+    pa: &'p PyModule,
+    data_type: &str,
+    values: &[T],
+) -> PyResult<&'p PyAny> {
+    let buffers = vec![pa.py().None(), arrow_buffer(pa, values)?.into()];
+    pa.getattr("Array")?.call_method1(
+        "from_buffers",
+        (pa.call_method0(data_type)?, values.len(), buffers),
+    )
+}
+
+fn arrow_bool<'p>(pa: &'p PyModule, values: &[bool]) -> PyResult<&'p PyAny> {
+    let buffers = vec![
+        pa.py().None(),
+        arrow_buffer(pa, &columnar::pack_bits(values))?.into(),
+    ];
+    pa.getattr("Array")?.call_method1(
+        "from_buffers",
+        (pa.call_method0("bool_")?, values.len(), buffers),
+    )
+}
+
+fn arrow_list<'p>(
+    pa: &'p PyModule,
+    names: &'p PyAny,
+    column: &ItemListColumn,
+) -> PyResult<&'p PyAny> {
+    let offsets = arrow_primitive(pa, "int32", &column.offsets)?;
+    let items = arrow_primitive(pa, "int32", &column.items)?;
+    let values = names.call_method1("take", (items,))?;
+    pa.getattr("ListArray")?
+        .call_method1("from_arrays", (offsets, values))
+}
+
+fn arrow_record_batch(pa: &PyModule, arrays: Vec<&PyAny>, names: &[&str]) -> PyResult<PyObject> {
+    let kwargs = [("names", names.to_vec())].into_py_dict(pa.py());
+    let batch = pa
+        .getattr("RecordBatch")?
+        .call_method("from_arrays", (arrays,), Some(kwargs))?;
+    Ok(batch.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! impl_entry {
+        ($($t:ty),*) => {
+            $(
+                impl Entry for $t {
+                    type Value = $t;
+
+                    fn value(&self) -> $t {
+                        *self
+                    }
+                }
+            )*
+        };
+    }
+    impl_entry!(i32, i64, f64);
+
+    #[test]
+    fn test_sparse_rows() {
+        // The second row stores an explicit zero in column 0
+        let indptr = [0, 2, 4, 4];
+        let indices = [2, 0, 0, 1];
+        let nonzero = [1.0, 0.5, 0.0, -2.0];
+        assert_eq!(
+            sparse_rows(&indptr, &indices, &nonzero, 3),
+            Some(vec![vec![0, 2], vec![1], vec![]])
+        );
+
+        assert_eq!(sparse_rows(&indptr, &indices, &nonzero, 2), None);
+        assert_eq!(sparse_rows(&[0, 5], &indices, &nonzero, 3), None);
+        assert_eq!(sparse_rows(&[0, -1], &indices, &nonzero, 3), None);
+    }
+
+    #[test]
+    fn test_long_rows() {
+        // Rows of transaction 1 are not contiguous, and one item is missing
+        let transaction_codes: [i64; 5] = [0, 1, 0, 1, 1];
+        let item_codes: [i64; 5] = [2, 0, 1, 0, -1];
+        assert_eq!(
+            long_rows(&transaction_codes, &item_codes, 3),
+            Some(vec![vec![1, 2], vec![0], vec![]])
+        );
+
+        assert_eq!(long_rows(&transaction_codes, &item_codes, 1), None);
+        assert_eq!(long_rows(&transaction_codes, &item_codes[..4], 3), None);
+    }
 }